@@ -0,0 +1,2101 @@
+// Automatic mode: settings, main loop and daemon
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike, Weekday};
+use chrono_tz::Tz;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command as ClapCommand};
+use daemonize::Daemonize;
+use log::{debug, error, info, log_enabled, warn, Level, LevelFilter};
+use serde::Serialize;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use termios::{tcsetattr, Termios, ECHO, ICANON, TCSANOW, VMIN, VTIME};
+#[cfg(feature = "x11")]
+use x11rb::connection::Connection;
+
+use crate::backlight::{self, Backlight, Levels};
+use crate::backend::{detect_backend, has_display, Backend, CrtcSelection, DryRunBackend};
+use crate::config::{
+    import_redshift, parse_weekday, redshift_config_path, try_load_config, validate_latitude, validate_longitude, Config,
+    WeekdaySchedule,
+};
+use crate::control;
+use crate::geoclue;
+use crate::gpsd;
+use crate::last_temp;
+use crate::location_cache;
+use crate::logging::{self, Throttle};
+use crate::metrics;
+use crate::notify::Notifier;
+use crate::override_file;
+use crate::daemon::{create_private_file, default_pid_file, err_file, log_file};
+use crate::location::{
+    find_city, get_longitude_offset, guess_location_from_system, named_timezones, offset_timezones, system_timezone_key,
+    timezone_coordinates, timezone_to_location_name, DEFAULT_COORDINATES, DEFAULT_TIMEZONE,
+};
+use crate::schedule::{
+    format_time, Clock, SystemClock,
+    align_to_daylight, clamp_day_times, mired_to_kelvin, parse_clock_time, polar_day, target_kelvin,
+    validate_temperatures, CurveSettings, CurveShape,
+    MINUTES_PER_DAY,
+};
+use crate::sun::{get_smoothed_day_times, MonthlyTimes, Twilight};
+use crate::suntimes::SunTimesFile;
+use crate::systemd;
+use crate::xsct::{
+    fade, parse_temp_limit, BrightnessScale, TempLimits, TempStatus, WhitePoint, GAMMA_LINEAR, TEMPERATURE_NIGHT,
+    TEMPERATURE_NORM,
+};
+#[cfg(feature = "x11")]
+use crate::xsct::{connected_outputs, open_display};
+
+const ESC_KEY: u8 = 27;
+const SMOOTH_INTERVAL_SECS: u64 = 5; // Update period in --transition mode
+const SUSPEND_GAP_SECS: u64 = 10; // Loop stall long enough to be a resume from suspend
+const SCHEDULE_STEP_MINUTES: usize = 15; // Row interval of --print-schedule
+const GAMMA_RANGE: std::ops::RangeInclusive<f64> = 0.1..=10.0; // Accepted --gamma exponents
+const BRIGHTNESS_FLOOR: f64 = 0.1; // Default lowest brightness ever applied
+const BRIGHTNESS_FLOOR_RANGE: std::ops::RangeInclusive<f64> = 0.05..=1.0; // --brightness-floor
+const REASSERT_TOLERANCE_K: i32 = 50; // Gamma read back this close to the target is ours
+const REASSERT_TOLERANCE_BRIGHTNESS: f64 = 0.02;
+const GPSD_REFRESH: Duration = Duration::from_secs(300); // Position update period with --gpsd
+const OVERRIDE_POLL: Duration = Duration::from_secs(1); // Check period of the override file
+const MINUTE_MARGIN: Duration = Duration::from_millis(20); // Wake just after the minute changes
+// Throttle keys of the errors that repeat on every update
+const APPLY_FAILED: &str = "apply";
+const READ_FAILED: &str = "read";
+const METRICS_FAILED: &str = "metrics";
+const NOTIFY_FAILED: &str = "notify";
+const OVERRIDE_FAILED: &str = "override";
+const BACKLIGHT_FAILED: &str = "backlight";
+const WATCH_BAR_WIDTH: usize = 20; // Sunrise to sunset bar of --watch
+
+const AFTER_HELP: &str = "\
+Commands:
+  xsct [...]     Integrated xsct (see colorwarm xsct -h)
+  ctl COMMAND    Send status, toggle, set KELVIN, auto, reload,
+                 profile NAME, pause [SECONDS] or resume to the daemon
+  status         Show whether the daemon is running and its temperature
+  stop           Stop the running daemon
+                 (both accept --pid-file PATH)
+  reset          Restore neutral 6500K gamma on all screens
+                 (within --min-temp K and --max-temp K when given)
+  get [--json]   Print the temperature of each screen, read from its gamma
+  test           Sweep the screens to 3000K and back, then restore them
+  install-service [OPTIONS]
+                 Write a systemd user unit running colorwarm with OPTIONS
+  uninstall-service
+                 Remove that unit
+
+Automatically manages screen temperature according to seasons:
+- Night: fixed night temperature (4500K by default)
+- Day: progressive variation between night and day temperatures
+- Automatically detects location from system timezone
+- Uses smoothed sunrise/sunset times adjusted for detected timezone
+- Supports over 300 cities and timezones worldwide
+- Includes integrated xsct functionality (no external dependency)
+
+Settings can be stored in $XDG_CONFIG_HOME/colorwarm/config.toml
+(default ~/.config/colorwarm/config.toml). Keys: day_temp, night_temp,
+latitude, longitude, transition_minutes, dawn_duration, dusk_duration,
+min_transition_time, max_transition_time, offset_minutes, curve, twilight,
+brightness, verbose, stops, profile, an [outputs] section of per-monitor
+offsets in Kelvin, [schedule.<weekday>] sections with offset, day_temp and
+night_temp, and [profiles.<name>] sections with day_temp, night_temp and
+brightness.
+Precedence, lowest first: defaults,
+COLORWARM_DAY_TEMP/COLORWARM_NIGHT_TEMP, config file, command line.";
+
+// Global state
+#[derive(Debug)]
+pub(crate) struct AppState {
+    pub(crate) verbose: bool,
+    pub(crate) timezone: String,
+    pub(crate) zone: Option<Tz>, // Clock of the schedule, the system one when None
+    pub(crate) clock: Rc<dyn Clock>, // Source of the time, fixed in tests
+    pub(crate) location_name: String,
+    pub(crate) daemon: bool,
+    pub(crate) pid_file: PathBuf,
+    pub(crate) coordinates: Option<(f64, f64)>, // Manual (latitude, longitude) override
+    pub(crate) gpsd: Option<String>, // gpsd address, polled for a new position
+    pub(crate) curve: CurveSettings,
+    pub(crate) brightness: f64,
+    pub(crate) brightness_floor: f64, // Lowest brightness applied, whatever asks for less
+    pub(crate) temp_limits: TempLimits, // Temperature range applied, whatever asks for more or less
+    pub(crate) smooth: bool, // Update every few seconds instead of once per minute
+    pub(crate) interval: Option<Duration>, // Fixed update period, replaces both of the above
+    pub(crate) reassert: Option<Duration>, // Period of the check for a gamma reset by other programs
+    pub(crate) fade_ms: u64, // Fade duration for large jumps (startup, resume)
+    pub(crate) brightness_scale: BrightnessScale, // Brightness steps of those fades (--perceptual-brightness)
+    pub(crate) forced_temp: Option<i32>, // Set through the control socket
+    pub(crate) file_override: Option<i32>, // Kelvin of the override file while it exists
+    pub(crate) paused: bool, // No adjustments, neutral gamma (control socket)
+    pub(crate) pause_until: Option<SystemTime>, // End of a timed pause
+    pub(crate) oneshot: bool, // Apply the current target once and exit
+    pub(crate) restore_on_exit: bool, // Neutral gamma when ESC or a signal stops us
+    pub(crate) json: bool, // One JSON object per update instead of text
+    pub(crate) watch: bool, // Status line redrawn in place instead of scrolling output
+    pub(crate) print_schedule: bool, // Print today's curve and exit
+    pub(crate) no_apply: bool, // Compute and log, but leave the gamma alone
+    pub(crate) metrics_file: Option<PathBuf>, // Prometheus textfile written on each update
+    pub(crate) notifier: Option<Notifier>, // Desktop notification on mode changes (--notify)
+    pub(crate) backlight: Option<(Backlight, Levels)>, // Panel backlight dimmed with the curve
+    pub(crate) output_offsets: HashMap<String, i32>, // Per-monitor offsets from the config file
+    pub(crate) excluded_outputs: Vec<String>, // Outputs never touched (--exclude)
+    pub(crate) weekdays: HashMap<Weekday, WeekdaySchedule>, // [schedule.<weekday>] overrides
+    pub(crate) min_transition_time: Option<i32>, // Earliest sunrise, minutes since midnight
+    pub(crate) max_transition_time: Option<i32>, // Latest sunset, minutes since midnight
+    pub(crate) selection: CrtcSelection, // Only these screen/CRTC (X11)
+    pub(crate) display: Option<String>, // X display instead of $DISPLAY
+    pub(crate) gamma: (f64, f64, f64), // Display gamma exponents (--gamma)
+    pub(crate) white_point: WhitePoint, // Planckian locus with --accurate
+    pub(crate) profile: Option<String>, // Profile in use, from the config file, --profile or the control socket
+    pub(crate) profile_switch: Option<String>, // Chosen through the control socket, kept across reloads
+    pub(crate) last_saved: Cell<Option<TempStatus>>, // Content of the last temperature file
+    pub(crate) throttle: Throttle, // Repeated errors, logged with a backoff
+}
+
+// Build the application state from command line and config file
+// (command line options take precedence over the file)
+fn build_state(args: &ArgMatches, config: &Config) -> Result<(AppState, MonthlyTimes), String> {
+    let verbose = args.get_flag("verbose") || config.verbose.unwrap_or(false);
+    let daemon = args.get_flag("daemon");
+    let pid_file = args.get_one::<PathBuf>("pid-file").cloned().unwrap_or_else(default_pid_file);
+    // Absolute, as the daemon writes it after changing to /
+    let pid_file = std::path::absolute(&pid_file).unwrap_or(pid_file);
+    // Absolute too, for the same reason
+    let metrics_file = args
+        .get_one::<PathBuf>("metrics-file")
+        .map(|path| std::path::absolute(path).unwrap_or_else(|_| path.clone()));
+    let oneshot = args.get_flag("oneshot");
+    let json = args.get_flag("json");
+    let watch = args.get_flag("watch");
+    let print_schedule = args.get_flag("print-schedule");
+    let no_apply = args.get_flag("no-apply");
+
+    // Manual location override (command line first, then config file)
+    let manual_coordinates = match (args.get_one::<f64>("lat").copied(), args.get_one::<f64>("lon").copied()) {
+        (Some(lat), Some(lon)) => Some((lat, lon)),
+        (None, None) => config.latitude.zip(config.longitude),
+        _ => return Err("--lat and --lon must be given together".to_string()),
+    };
+
+    // Bundled city coordinates, or the gpsd or GeoClue position when asked
+    // for, falling back to the timezone estimate
+    let city = args.get_one::<String>("city").map(|query| find_city(query)).transpose()?;
+    let gpsd = args.get_one::<String>("gpsd").cloned();
+    let located = match (manual_coordinates, city, gpsd.as_deref()) {
+        (Some((lat, lon)), _, _) => Some(((lat, lon), format!("{:.4}, {:.4} (manual)", lat, lon))),
+        (None, Some(city), _) => Some(((city.latitude, city.longitude), city.name)),
+        (None, None, Some(address)) => match gpsd::locate(address) {
+            Ok((lat, lon)) => Some(((lat, lon), format!("{:.4}, {:.4} (gpsd)", lat, lon))),
+            Err(e) => {
+                warn!("gpsd position unavailable ({}), using the timezone until there is a fix", e);
+                None
+            }
+        },
+        (None, None, None) if args.get_flag("geoclue") => match geoclue::locate() {
+            Ok((lat, lon)) => Some(((lat, lon), format!("{:.4}, {:.4} (GeoClue)", lat, lon))),
+            Err(e) => {
+                warn!("GeoClue location unavailable ({}), using the timezone", e);
+                None
+            }
+        },
+        (None, None, None) => None,
+    };
+
+    // The schedule runs on the clock of --timezone, else of the system
+    let zone = args.get_one::<Tz>("timezone").copied();
+
+    // Try to detect location from system, unless given manually
+    let location = match (&located, zone) {
+        (Some(_), _) => None,
+        (None, Some(zone)) => {
+            let name = timezone_to_location_name(zone.name()).unwrap_or_else(|| zone.name().to_string());
+            Some((zone.name().to_string(), name))
+        }
+        (None, None) => detect_system_location(),
+    };
+    let (timezone, location_name, coordinates, zone) = match (located, location) {
+        (Some(((lat, lon), name)), _) => {
+            debug!("Location: {}", name);
+            (zone.map_or("Local", |zone| zone.name()).to_string(), name, Some((lat, lon)), zone)
+        },
+        (None, Some((tz, name))) => {
+            debug!("Detected timezone: {}", tz);
+            debug!("Location: {}", name);
+            let zone = tz.parse::<Tz>().ok();
+            (tz, name, None, zone)
+        },
+        (None, None) => {
+            // Default to Brussels if detection fails
+            let default_tz = DEFAULT_TIMEZONE.to_string();
+            let default_name = "Brussels, Belgium (default)".to_string();
+            
+            debug!("Could not detect timezone, using default: {}", default_tz);
+            (default_tz, default_name, Some(DEFAULT_COORDINATES), None)
+        }
+    };
+
+    // Day/night temperatures (command line first, then config file)
+    let day_temp = args.get_one::<i32>("day-temp").copied()
+        .or_else(|| args.get_one::<f64>("day-mired").map(|&mired| mired_to_kelvin(mired)))
+        .or(config.day_temp)
+        .unwrap_or(TEMPERATURE_NORM);
+    let night_temp = args.get_one::<i32>("night-temp").copied()
+        .or_else(|| args.get_one::<f64>("night-mired").map(|&mired| mired_to_kelvin(mired)))
+        .or(config.night_temp)
+        .unwrap_or(TEMPERATURE_NIGHT);
+    validate_temperatures(day_temp, night_temp)?;
+    let mut weekdays = HashMap::new();
+    for (name, schedule) in &config.schedule {
+        validate_temperatures(schedule.day_temp.unwrap_or(day_temp), schedule.night_temp.unwrap_or(night_temp))
+            .map_err(|e| format!("[schedule.{}]: {}", name, e))?;
+        weekdays.insert(parse_weekday(name)?, *schedule);
+    }
+    let transition_minutes = args.get_one::<i32>("transition-minutes").copied()
+        .or(config.transition_minutes);
+    let dawn_minutes = args.get_one::<i32>("dawn-duration").copied().or(config.dawn_duration);
+    let dusk_minutes = args.get_one::<i32>("dusk-duration").copied().or(config.dusk_duration);
+    let clock_time = |arg: &str, key: &Option<String>| -> Result<Option<i32>, String> {
+        match args.get_one::<i32>(arg) {
+            Some(&minutes) => Ok(Some(minutes)),
+            None => key.as_deref().map(parse_clock_time).transpose(),
+        }
+    };
+    let min_transition_time = clock_time("min-transition-time", &config.min_transition_time)?;
+    let max_transition_time = clock_time("max-transition-time", &config.max_transition_time)?;
+    let shape = args.get_one::<CurveShape>("curve").copied()
+        .or(config.curve)
+        .unwrap_or_default();
+    let invert = args.get_flag("invert");
+    let mut stops = config.stops.clone();
+    stops.sort_unstable();
+    let smooth = args.get_flag("transition");
+    let interval = args.get_one::<u64>("interval").copied().map(Duration::from_secs);
+    let reassert = args.get_one::<u64>("reassert").copied().map(Duration::from_secs);
+    let fade_ms = args.get_one::<u64>("fade-ms").copied().unwrap_or(0);
+    let brightness = config.brightness.unwrap_or(1.0);
+    let brightness_floor = args.get_one::<f64>("brightness-floor").copied().unwrap_or(BRIGHTNESS_FLOOR);
+    if brightness < brightness_floor {
+        warn!("Brightness {} is below the floor, using {}", brightness, brightness_floor);
+    }
+    let temp_limits = TempLimits::new(
+        args.get_one::<i32>("min-temp").copied(),
+        args.get_one::<i32>("max-temp").copied(),
+    )?;
+    let backlight = match args.get_one::<Levels>("backlight") {
+        Some(&levels) => {
+            let device = args.get_one::<String>("backlight-device").map(String::as_str);
+            let backlight = Backlight::open(Path::new(backlight::SYSFS_DIR), device)?;
+            debug!("Backlight device {}, {}% at night, {}% by day", backlight.name(), levels.night, levels.day);
+            Some((backlight, levels))
+        }
+        None => None,
+    };
+
+    let state = AppState {
+        verbose,
+        timezone,
+        zone,
+        clock: Rc::new(SystemClock),
+        location_name,
+        daemon,
+        pid_file,
+        coordinates,
+        gpsd: gpsd.filter(|_| manual_coordinates.is_none()),
+        curve: CurveSettings {
+            day_temp,
+            night_temp,
+            transition_minutes,
+            dawn_minutes,
+            dusk_minutes,
+            shape,
+            invert,
+            stops,
+        },
+        brightness: brightness.max(brightness_floor),
+        brightness_floor,
+        temp_limits,
+        smooth,
+        interval,
+        reassert,
+        fade_ms,
+        brightness_scale: if args.get_flag("perceptual-brightness") {
+            BrightnessScale::Perceptual
+        } else {
+            BrightnessScale::Linear
+        },
+        forced_temp: None,
+        file_override: None,
+        paused: false,
+        pause_until: None,
+        oneshot,
+        restore_on_exit: args.get_flag("restore-on-exit"),
+        json,
+        watch,
+        print_schedule,
+        no_apply,
+        metrics_file,
+        notifier: args.get_flag("notify").then(Notifier::new),
+        backlight,
+        output_offsets: config.outputs.clone(),
+        excluded_outputs: args.get_many::<String>("exclude").unwrap_or_default().cloned().collect(),
+        weekdays,
+        min_transition_time,
+        max_transition_time,
+        selection: CrtcSelection {
+            screen: args.get_one::<usize>("screen").copied(),
+            crtc: args.get_one::<usize>("crtc").copied(),
+        },
+        display: args.get_one::<String>("display").cloned(),
+        gamma: args.get_one::<(f64, f64, f64)>("gamma").copied().unwrap_or(GAMMA_LINEAR),
+        white_point: if args.get_flag("accurate") { WhitePoint::Planckian } else { WhitePoint::Empirical },
+        profile: config.profile.clone(),
+        profile_switch: None,
+        last_saved: Cell::new(None),
+        throttle: Throttle::new(),
+    };
+
+    // Sun times in the clock of the schedule, computed from the bundled
+    // coordinates of the timezone when there are no others
+    let clock = state.zone.map_or("Local", |zone| zone.name());
+    let monthly_times = match state.coordinates.or_else(|| timezone_coordinates(&state.timezone)) {
+        Some((latitude, longitude)) => MonthlyTimes::new_for_coordinates(clock, latitude, longitude),
+        None => MonthlyTimes::new_for_timezone(clock),
+    };
+    let offset = args.get_one::<i32>("offset").copied()
+        .or(config.offset_minutes)
+        .unwrap_or(0);
+    let twilight = args.get_one::<Twilight>("twilight").copied()
+        .or(config.twilight)
+        .unwrap_or_default();
+    if twilight != Twilight::None && !monthly_times.has_coordinates() && state.gpsd.is_none() {
+        warn!("Twilight needs coordinates (--lat/--lon, --city...), using the horizon crossing");
+    }
+    // Absolute, as the file is read again after the daemon changed to /
+    let sun_times = args
+        .get_one::<PathBuf>("suntimes-file")
+        .map(|path| SunTimesFile::open(&std::path::absolute(path).unwrap_or_else(|_| path.clone())))
+        .transpose()?;
+    let monthly_times = monthly_times.with_offset(offset).with_twilight(twilight).with_sun_times(sun_times);
+
+    Ok((state, monthly_times))
+}
+
+// Timezone and location name of the system setting, reused from the
+// previous run while the setting is unchanged
+fn detect_system_location() -> Option<(String, String)> {
+    let key = system_timezone_key();
+    if let Some(location) = location_cache::load(&key) {
+        return Some(location);
+    }
+    let location = guess_location_from_system()?;
+    if let Err(e) = location_cache::save(&key, &location) {
+        debug!("Cannot save the detected location: {}", e);
+    }
+    Some(location)
+}
+
+impl AppState {
+    /// End a pause, the caller applies the temperature again
+    pub(crate) fn resume(&mut self) {
+        self.paused = false;
+        self.pause_until = None;
+    }
+
+    /// Temperature holding instead of the schedule: set through the control
+    /// socket, else the override file
+    pub(crate) fn forced(&self) -> Option<i32> {
+        self.forced_temp.or(self.file_override)
+    }
+
+    /// Current time in the clock of the schedule
+    pub(crate) fn now(&self) -> NaiveDateTime {
+        self.clock.now(self.zone)
+    }
+
+    /// `temp` within the safety limits: the temperature range and the
+    /// brightness floor
+    pub(crate) fn limited(&self, temp: TempStatus) -> TempStatus {
+        let temp = self.temp_limits.clamp(temp);
+        TempStatus { brightness: temp.brightness.max(self.brightness_floor), ..temp }
+    }
+
+    // A timed pause has run out
+    fn pause_expired(&self) -> bool {
+        self.paused && self.pause_until.is_some_and(|until| self.clock.system_time() >= until)
+    }
+}
+
+// Config file and environment, completed by redshift.conf with --import-redshift,
+// with a profile applied: `profile` if given, else --profile, else the
+// config file's
+fn load_settings(args: &ArgMatches, profile: Option<&str>) -> Result<Config, String> {
+    let mut config = try_load_config()?;
+    if args.contains_id("import-redshift") {
+        let path = match args.get_one::<PathBuf>("import-redshift") {
+            Some(path) => path.clone(),
+            None => redshift_config_path().ok_or("cannot locate redshift.conf, give its path")?,
+        };
+        config = config.or(import_redshift(&path)?);
+    }
+
+    if let Some(name) = profile.or(args.get_one::<String>("profile").map(String::as_str)) {
+        config.profile = Some(name.to_string());
+    }
+    config.apply_profile()
+}
+
+// Re-read the config file on SIGHUP, keeping the current settings if it is invalid
+pub(crate) fn reload_state(args: &ArgMatches, state: &mut AppState, monthly_times: &mut MonthlyTimes) {
+    let profile_switch = state.profile_switch.clone();
+    match load_settings(args, profile_switch.as_deref()).and_then(|config| build_state(args, &config)) {
+        Ok((new_state, new_times)) => {
+            *state = AppState {
+                profile_switch,
+                file_override: state.file_override,
+                paused: state.paused,
+                pause_until: state.pause_until,
+                clock: state.clock.clone(),
+                ..new_state
+            };
+            *monthly_times = new_times;
+            info!("Configuration reloaded");
+        }
+        Err(e) => warn!("Configuration reload failed, keeping previous settings: {}", e),
+    }
+}
+
+// Settings of another profile, from a fresh read of the config file
+// (control socket). The choice holds until the next switch, also across
+// reloads.
+pub(crate) fn switch_profile(
+    args: &ArgMatches,
+    state: &mut AppState,
+    monthly_times: &mut MonthlyTimes,
+    name: &str,
+) -> Result<(), String> {
+    let (new_state, new_times) = load_settings(args, Some(name)).and_then(|config| build_state(args, &config))?;
+    *state = AppState {
+        profile_switch: Some(name.to_string()),
+        file_override: state.file_override,
+        paused: state.paused,
+        pause_until: state.pause_until,
+        clock: state.clock.clone(),
+        ..new_state
+    };
+    *monthly_times = new_times;
+    info!("Switched to profile {}", name);
+    Ok(())
+}
+
+// Today's sunrise, sunset and curve, with the [schedule.<weekday>] override applied
+pub(crate) fn todays_schedule(state: &AppState, monthly_times: &MonthlyTimes) -> (i32, i32, CurveSettings) {
+    schedule_on(state, monthly_times, state.now().date())
+}
+
+// Sunrise, sunset and curve for any date, within --min/--max-transition-time
+fn schedule_on(state: &AppState, monthly_times: &MonthlyTimes, date: NaiveDate) -> (i32, i32, CurveSettings) {
+    let (sunrise, sunset) = get_smoothed_day_times(monthly_times, date);
+    let (sunrise, sunset, curve) = match state.weekdays.get(&date.weekday()) {
+        Some(schedule) => {
+            let shift = schedule.offset.unwrap_or(0);
+            let curve = CurveSettings {
+                day_temp: schedule.day_temp.unwrap_or(state.curve.day_temp),
+                night_temp: schedule.night_temp.unwrap_or(state.curve.night_temp),
+                ..state.curve.clone()
+            };
+            (sunrise + shift, sunset + shift, curve)
+        }
+        None => (sunrise, sunset, state.curve.clone()),
+    };
+    let (sunrise, sunset) = clamp_day_times(sunrise, sunset, state.min_transition_time, state.max_transition_time);
+    (sunrise, sunset, curve)
+}
+
+// Apply the exact temperature for the current second (smooth transition mode)
+fn apply_smooth_step(state: &AppState, monthly_times: &MonthlyTimes, backend: &dyn Backend) {
+    if state.paused {
+        return;
+    }
+    set_temperature_logged(state, backend, current_target(state, monthly_times));
+}
+
+// Apply a temperature, logging a failure through the throttle: it would
+// come back on every update while the display is gone
+fn set_temperature_logged(state: &AppState, backend: &dyn Backend, temp: TempStatus) -> bool {
+    // Last stop before the gamma: never dark enough to lock the user out,
+    // never outside --min-temp/--max-temp
+    match backend.set_temperature(state.limited(temp)) {
+        Ok(()) => {
+            state.throttle.clear(APPLY_FAILED);
+            true
+        }
+        Err(e) => {
+            state.throttle.log(APPLY_FAILED, Level::Error, format!("Error setting temperature: {}", e));
+            false
+        }
+    }
+}
+
+// Current gamma, logging a failure through the throttle
+fn get_temperature_logged(state: &AppState, backend: &dyn Backend) -> Option<TempStatus> {
+    match backend.get_temperature() {
+        Ok(current) => {
+            state.throttle.clear(READ_FAILED);
+            Some(current)
+        }
+        Err(e) => {
+            state.throttle.log(READ_FAILED, Level::Error, format!("Error reading current temperature: {}", e));
+            None
+        }
+    }
+}
+
+// Target temperature for right now
+fn current_target(state: &AppState, monthly_times: &MonthlyTimes) -> TempStatus {
+    let (sunrise, sunset, curve) = todays_schedule(state, monthly_times);
+    let now_minutes = state.clock.local_time_precise(state.zone);
+    let kelvin = state.forced()
+        .unwrap_or_else(|| target_kelvin(now_minutes, sunrise, sunset, &curve));
+    TempStatus { temp: kelvin, brightness: state.brightness }
+}
+
+// One info line per day with the new day's schedule, a marker to grep the
+// log for
+fn log_daily_summary(state: &AppState, monthly_times: &MonthlyTimes) {
+    let (sunrise, sunset, curve) = todays_schedule(state, monthly_times);
+    let kelvins: Vec<i32> = (0..MINUTES_PER_DAY)
+        .map(|minute| target_kelvin(minute as f64, sunrise, sunset, &curve))
+        .collect();
+    let lowest = kelvins.iter().min().copied().unwrap_or(curve.night_temp);
+    let highest = kelvins.iter().max().copied().unwrap_or(curve.day_temp);
+    let daylight = match polar_day(sunrise, sunset) {
+        Some(true) => "polar day".to_string(),
+        Some(false) => "polar night".to_string(),
+        None => format!(
+            "sunrise {}, sunset {}, day length {}",
+            format_time(sunrise),
+            format_time(sunset),
+            format_time(sunset - sunrise)
+        ),
+    };
+    info!("New day {}: {}, {}K to {}K", state.now().format("%Y-%m-%d"), daylight, lowest, highest);
+}
+
+// Poll gpsd for a new position (--gpsd). Without a fix, the last known
+// position is kept.
+fn refresh_gpsd_position(state: &mut AppState, monthly_times: &mut MonthlyTimes) {
+    let Some(address) = &state.gpsd else {
+        return;
+    };
+    match gpsd::locate(address) {
+        Ok((lat, lon)) => {
+            if state.coordinates != Some((lat, lon)) {
+                debug!("gpsd position: {:.4}, {:.4}", lat, lon);
+            }
+            state.coordinates = Some((lat, lon));
+            state.location_name = format!("{:.4}, {:.4} (gpsd)", lat, lon);
+            monthly_times.set_coordinates(lat, lon);
+        }
+        Err(e) => debug!("gpsd position unavailable ({}), keeping the last one", e),
+    }
+}
+
+// Apply the target again when the gamma no longer matches it, after a game
+// or video player reset the ramps (--reassert). Nothing is sent while the
+// gamma is still ours.
+fn reassert_temperature(state: &AppState, monthly_times: &MonthlyTimes, backend: &dyn Backend) {
+    if state.paused {
+        return;
+    }
+    let target = current_target(state, monthly_times);
+    match get_temperature_logged(state, backend) {
+        Some(current)
+            if (current.temp - target.temp).abs() <= REASSERT_TOLERANCE_K
+                && (current.brightness - target.brightness).abs() <= REASSERT_TOLERANCE_BRIGHTNESS => {}
+        Some(current) => {
+            debug!("Gamma changed to {}K behind our back, applying {}K again", current.temp, target.temp);
+            set_temperature_logged(state, backend, target);
+        }
+        None => {}
+    }
+}
+
+// Fade from the current gamma to the target, used after large jumps
+fn fade_to_target(state: &AppState, monthly_times: &MonthlyTimes, backend: &dyn Backend) {
+    if state.fade_ms > 0 && !state.paused {
+        let target = current_target(state, monthly_times);
+        if let Some(current) = get_temperature_logged(state, backend) {
+            fade(current, target, state.fade_ms, state.brightness_scale, |step| {
+                set_temperature_logged(state, backend, step);
+            });
+        }
+    }
+}
+
+// Position of `kelvin` from the night (0) to the day (1) temperature,
+// which the backlight follows
+fn curve_fraction(curve: &CurveSettings, kelvin: i32) -> f64 {
+    if curve.day_temp == curve.night_temp {
+        return 1.0;
+    }
+    ((kelvin - curve.night_temp) as f64 / (curve.day_temp - curve.night_temp) as f64).clamp(0.0, 1.0)
+}
+
+// Manage brightness cycle - CALLED EVERY MINUTE (or every --interval),
+// at the time of the state's clock (the tests drive it through a day)
+fn manage_brightness_cycle(state: &AppState, monthly_times: &MonthlyTimes, backend: &dyn Backend) {
+    if state.paused {
+        return;
+    }
+
+    let now = state.now();
+    let current_minutes = (now.hour() * 60 + now.minute()) as i32;
+    let (sunrise, sunset, curve) = schedule_on(state, monthly_times, now.date());
+    
+    // Exact time, so that sub-minute intervals progress between minutes
+    let precise_minutes = now.num_seconds_from_midnight() as f64 / 60.0;
+    let kelvin = state.forced()
+        .unwrap_or_else(|| target_kelvin(precise_minutes, sunrise, sunset, &curve));
+    // What reaches the gamma, and so what is saved and reported
+    let applied = state.limited(TempStatus { temp: kelvin, brightness: state.brightness });
+    
+    if !set_temperature_logged(state, backend, applied) {
+        // Keep running: the display may come back (monitor replugged, X restarted)
+        return;
+    }
+
+    // Ready for systemd once a temperature has actually been applied
+    systemd::notify_ready();
+    save_last_temperature(state, applied);
+    let mode = day_phase(&curve, current_minutes, sunrise, sunset, kelvin);
+    if let Some(path) = &state.metrics_file {
+        match metrics::write(path, applied, mode) {
+            Ok(()) => state.throttle.clear(METRICS_FAILED),
+            Err(e) => state.throttle.log(METRICS_FAILED, Level::Warn, format!("Cannot write {}: {}", path.display(), e)),
+        }
+    }
+    if let Some(notifier) = &state.notifier {
+        match notifier.update(mode, applied.temp) {
+            Ok(()) => state.throttle.clear(NOTIFY_FAILED),
+            Err(e) => state.throttle.log(NOTIFY_FAILED, Level::Warn, format!("Cannot send a notification: {}", e)),
+        }
+    }
+    if let (Some((backlight, levels)), false) = (&state.backlight, state.no_apply) {
+        match backlight.set_percent(levels.at(curve_fraction(&curve, kelvin))) {
+            Ok(_) => state.throttle.clear(BACKLIGHT_FAILED),
+            Err(e) => state.throttle.log(BACKLIGHT_FAILED, Level::Warn, format!("Cannot set the backlight: {}", e)),
+        }
+    }
+    debug!("Setting to {}K at {} in {} (sunrise: {}, sunset: {})",
+           kelvin,
+           format_time(current_minutes),
+           state.location_name,
+           format_time(sunrise),
+           format_time(sunset));
+
+    if state.json {
+        let line = StatusLine {
+            time: format_time(current_minutes),
+            kelvin,
+            brightness: state.brightness,
+            sunrise: format_time(sunrise),
+            sunset: format_time(sunset),
+            mode,
+        };
+        match serde_json::to_string(&line) {
+            Ok(json) => println!("{}", json),
+            Err(e) => error!("Error encoding JSON status: {}", e),
+        }
+    } else if !state.daemon && !state.watch {
+        // Even in non-verbose mode, show minimal feedback
+        println!("[{}] {}K",
+                 format_time(current_minutes),
+                 kelvin);
+    }
+    
+    io::stdout().flush().unwrap();
+}
+
+// Keep the applied temperature for the next start, writing only on change
+fn save_last_temperature(state: &AppState, temp: TempStatus) {
+    if state.no_apply || state.last_saved.get() == Some(temp) {
+        return;
+    }
+    if let Err(e) = last_temp::save(temp) {
+        warn!("Cannot save the last temperature: {}", e);
+    }
+    // Not retried on error, to avoid one message per update
+    state.last_saved.set(Some(temp));
+}
+
+// Display backend, or a stand-in that changes nothing with --no-apply
+fn select_backend(state: &AppState) -> Box<dyn Backend> {
+    if state.no_apply {
+        Box::new(DryRunBackend::new())
+    } else {
+        detect_backend(state.selection, state.display.as_deref())
+    }
+}
+
+// Table of today's temperatures every SCHEDULE_STEP_MINUTES (--print-schedule)
+fn print_schedule(state: &AppState, monthly_times: &MonthlyTimes) {
+    let (sunrise, sunset, curve) = todays_schedule(state, monthly_times);
+
+    println!("Location: {}", state.location_name);
+    match polar_day(sunrise, sunset) {
+        Some(true) => println!("Polar day: the sun does not set today"),
+        Some(false) => println!("Polar night: the sun does not rise today"),
+        None => println!("Sunrise: {}  Sunset: {}", format_time(sunrise), format_time(sunset)),
+    }
+    println!();
+    for minutes in (0..MINUTES_PER_DAY).step_by(SCHEDULE_STEP_MINUTES) {
+        let kelvin = target_kelvin(minutes as f64, sunrise, sunset, &curve);
+        println!("{}  {}K", format_time(minutes), kelvin);
+    }
+}
+
+// Every timezone of the location name and longitude offset tables, "-"
+// where a table has no entry and a count where it has several
+// (--dump-timezone-db)
+fn dump_timezone_db() {
+    let mut entries: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for timezone in named_timezones() {
+        entries.entry(timezone).or_default().0 += 1;
+    }
+    for timezone in offset_timezones() {
+        entries.entry(timezone).or_default().1 += 1;
+    }
+
+    let listed = |count: usize, value: String| match count {
+        0 => "-".to_string(),
+        1 => value,
+        _ => format!("{} (listed {} times)", value, count),
+    };
+    println!("timezone\tlocation\toffset");
+    for (timezone, (names, offsets)) in entries {
+        let name = timezone_to_location_name(timezone).unwrap_or_default();
+        let offset = get_longitude_offset(timezone).to_string();
+        println!("{}\t{}\t{}", timezone, listed(names, name), listed(offsets, offset));
+    }
+}
+
+// Connected outputs of each screen with their CRTC index, gamma ramp size and
+// current temperature: the names for [outputs] and --exclude, the indexes
+// for --crtc (--list-outputs)
+#[cfg(feature = "x11")]
+fn list_outputs(display: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let (conn, _) = open_display(display)?;
+    for screen in 0..conn.setup().roots.len() {
+        println!("Screen {}:", screen);
+        for output in connected_outputs(&conn, screen)? {
+            match output.crtc {
+                Some(crtc) if crtc.ramp_size == 0 => println!("  {}: CRTC {}, no gamma ramp", output.name, crtc.index),
+                Some(crtc) => println!(
+                    "  {}: CRTC {}, gamma ramp of {} entries, ~{}K brightness {:.2}",
+                    output.name, crtc.index, crtc.ramp_size, crtc.temp.temp, crtc.temp.brightness
+                ),
+                None => println!("  {}: off (no CRTC)", output.name),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "x11"))]
+fn list_outputs(_display: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--list-outputs needs X11 support (built without the x11 feature)".into())
+}
+
+// One line of --json output
+#[derive(Serialize)]
+struct StatusLine {
+    time: String,
+    kelvin: i32,
+    brightness: f64,
+    sunrise: String,
+    sunset: String,
+    mode: &'static str,
+}
+
+// "night" outside daylight, "day" on the plateau, "transition" on the ramps
+fn day_phase(curve: &CurveSettings, now_minutes: i32, sunrise: i32, sunset: i32, kelvin: i32) -> &'static str {
+    if let Some(sun_up) = polar_day(sunrise, sunset) {
+        if sun_up { "day" } else { "night" }
+    } else if !(sunrise as f64..sunset as f64).contains(&align_to_daylight(now_minutes as f64, sunrise, sunset)) {
+        "night"
+    } else if kelvin == if curve.invert { curve.night_temp } else { curve.day_temp } {
+        "day"
+    } else {
+        "transition"
+    }
+}
+
+// Status line of --watch: clock, kelvin, position between sunrise and sunset
+// and the next change of phase
+fn watch_line(state: &AppState, monthly_times: &MonthlyTimes, now: NaiveDateTime) -> String {
+    let (sunrise, sunset, curve) = schedule_on(state, monthly_times, now.date());
+    let precise_minutes = now.num_seconds_from_midnight() as f64 / 60.0;
+    let kelvin = state.forced()
+        .unwrap_or_else(|| target_kelvin(precise_minutes, sunrise, sunset, &curve));
+
+    let daylight = match polar_day(sunrise, sunset) {
+        Some(sun_up) => if sun_up { 1.0 } else { 0.0 },
+        None => ((align_to_daylight(precise_minutes, sunrise, sunset) - sunrise as f64)
+            / (sunset - sunrise) as f64)
+            .clamp(0.0, 1.0),
+    };
+    let filled = (daylight * WATCH_BAR_WIDTH as f64).round() as usize;
+    let bar = format!("{}{}", "#".repeat(filled), "-".repeat(WATCH_BAR_WIDTH - filled));
+
+    let status = if state.paused {
+        "paused".to_string()
+    } else if state.forced().is_some() {
+        "forced".to_string()
+    } else {
+        match next_transition(&curve, precise_minutes as i32, sunrise, sunset) {
+            Some((minutes, phase)) => format!("{} at {}", phase, format_time(minutes)),
+            None => "no change today".to_string(),
+        }
+    };
+    format!("{}  {}K  {} [{}] {}  next: {}",
+            now.format("%H:%M:%S"),
+            kelvin,
+            format_time(sunrise),
+            bar,
+            format_time(sunset),
+            status)
+}
+
+// First minute of the next day phase (see day_phase) after `now_minutes`,
+// with the phase it starts
+fn next_transition(curve: &CurveSettings, now_minutes: i32, sunrise: i32, sunset: i32) -> Option<(i32, &'static str)> {
+    let phase_at = |minutes: i32| {
+        day_phase(curve, minutes, sunrise, sunset, target_kelvin(minutes as f64, sunrise, sunset, curve))
+    };
+    let current = phase_at(now_minutes);
+    (1..MINUTES_PER_DAY)
+        .map(|ahead| (now_minutes + ahead).rem_euclid(MINUTES_PER_DAY))
+        .map(|minutes| (minutes, phase_at(minutes)))
+        .find(|&(_, phase)| phase != current)
+}
+
+// Wall clock time of the next sunrise, sunset or end of a ramp, so a long
+// --interval does not reach it late (None without --interval, or when the
+// temperature is held)
+fn next_boundary(state: &AppState, monthly_times: &MonthlyTimes) -> Option<SystemTime> {
+    if state.interval.is_none() || state.forced().is_some() || state.paused {
+        return None;
+    }
+    let (sunrise, sunset, curve) = todays_schedule(state, monthly_times);
+    let now = state.now();
+    let now_minutes = (now.hour() * 60 + now.minute()) as i32;
+    let (minutes, _) = next_transition(&curve, now_minutes, sunrise, sunset)?;
+    let ahead = (minutes - now_minutes).rem_euclid(MINUTES_PER_DAY) as u64;
+    let into_minute = Duration::new(now.second() as u64, now.nanosecond() % 1_000_000_000);
+    Some(state.clock.system_time() + (Duration::from_secs(ahead * 60) + MINUTE_MARGIN).saturating_sub(into_minute))
+}
+
+// Read the override file again. Returns true when its value changed, the
+// caller then applies the temperature. An invalid file (or one caught half
+// written) keeps the previous value.
+fn poll_override_file(state: &mut AppState) -> bool {
+    let value = match override_file::read(&override_file::path()) {
+        Ok(value) => {
+            state.throttle.clear(OVERRIDE_FAILED);
+            value
+        }
+        Err(e) => {
+            state.throttle.log(OVERRIDE_FAILED, Level::Warn, format!("Ignoring the override file: {}", e));
+            return false;
+        }
+    };
+    if value == state.file_override {
+        return false;
+    }
+    match value {
+        Some(kelvin) => info!("Override file: holding {}K", kelvin),
+        None => info!("Override file gone, back to the schedule"),
+    }
+    state.file_override = value;
+    true
+}
+
+// Redraw the --watch line over the previous one
+fn draw_watch_line(state: &AppState, monthly_times: &MonthlyTimes) {
+    // \x1b[K clears what a longer previous line left behind
+    print!("\r{}\x1b[K", watch_line(state, monthly_times, state.now()));
+    io::stdout().flush().unwrap();
+}
+
+// Neutral gamma (within --min-temp/--max-temp) on every output: the
+// per-output offsets are left out of these ramps, kept for the next update
+pub(crate) fn set_neutral(state: &AppState, backend: &dyn Backend) -> Result<TempStatus, Box<dyn std::error::Error>> {
+    let neutral = state.limited(TempStatus { temp: TEMPERATURE_NORM, brightness: 1.0 });
+    backend.set_output_offsets(&HashMap::new());
+    let result = backend.set_temperature(neutral);
+    backend.set_output_offsets(&state.output_offsets);
+    result.map(|()| neutral)
+}
+
+// Common exit path for ESC and termination signals
+fn shutdown(state: &AppState, backend: &dyn Backend) {
+    info!("Exiting");
+
+    if state.restore_on_exit && !state.no_apply {
+        match set_neutral(state, backend) {
+            Ok(neutral) => debug!("Restored {}K", neutral.temp),
+            Err(e) => warn!("Cannot restore the screen temperature: {}", e),
+        }
+    }
+
+    if state.daemon {
+        if let Err(e) = fs::remove_file(&state.pid_file) {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Cannot remove {}: {}", state.pid_file.display(), e);
+            }
+        }
+    }
+}
+
+// Command line of the automatic mode (xsct and ctl are dispatched before)
+fn command() -> ClapCommand {
+    ClapCommand::new("colorwarm")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Automatically manages screen temperature according to seasons")
+        .after_help(AFTER_HELP)
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Display execution details")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("log-level")
+                .long("log-level")
+                .help("Log messages up to this level (default info, debug with -v)")
+                .value_name("LEVEL")
+                .value_parser(
+                    PossibleValuesParser::new(logging::LEVELS)
+                        .map(|level| level.parse::<LevelFilter>().unwrap_or(LevelFilter::Info)),
+                ),
+        )
+        .arg(
+            Arg::new("daemon")
+                .short('d')
+                .long("daemon")
+                .help("Run in background (daemon mode)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("pid-file")
+                .long("pid-file")
+                .help("Daemon pid file (default $XDG_RUNTIME_DIR/colorwarm.pid)")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("import-redshift")
+                .long("import-redshift")
+                .help("Use redshift.conf settings missing from the colorwarm config")
+                .value_name("PATH")
+                .num_args(0..=1)
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("lat")
+                .long("lat")
+                .help("Latitude for manual location (-90 to 90, south is negative)")
+                .value_name("DEG")
+                .value_parser(parse_latitude)
+                .allow_negative_numbers(true)
+                .requires("lon"),
+        )
+        .arg(
+            Arg::new("lon")
+                .long("lon")
+                .help("Longitude for manual location (-180 to 180, west is negative)")
+                .value_name("DEG")
+                .value_parser(parse_longitude)
+                .allow_negative_numbers(true)
+                .requires("lat"),
+        )
+        .arg(
+            Arg::new("city")
+                .long("city")
+                .help("Location from a city or timezone name (e.g. Tokyo)")
+                .value_name("NAME")
+                .conflicts_with_all(["lat", "lon"]),
+        )
+        .arg(
+            Arg::new("timezone")
+                .long("timezone")
+                .help("Run the schedule on the clock of this IANA timezone (e.g. Asia/Tokyo), also the location without other options")
+                .value_name("TZ")
+                .value_parser(|s: &str| s.parse::<Tz>()),
+        )
+        .arg(
+            Arg::new("gpsd")
+                .long("gpsd")
+                .help("Follow the position from gpsd (default localhost:2947), for vehicles")
+                .value_name("HOST:PORT")
+                .num_args(0..=1)
+                .default_missing_value(gpsd::DEFAULT_ADDRESS)
+                .conflicts_with_all(["lat", "lon", "city"]),
+        )
+        .arg(
+            Arg::new("geoclue")
+                .long("geoclue")
+                .help("Locate with GeoClue2 (falls back to the timezone)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .help("Use the settings of a [profiles.NAME] section of the config file")
+                .value_name("NAME"),
+        )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .help("Never change the gamma of this output (X11, repeatable)")
+                .value_name("OUTPUT")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("display")
+                .long("display")
+                .help("X display to adjust, e.g. :1 (all of its screens), instead of $DISPLAY")
+                .value_name("NAME"),
+        )
+        .arg(
+            Arg::new("screen")
+                .long("screen")
+                .help("Only adjust the screen with this zero-based index (X11)")
+                .value_name("N")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("crtc")
+                .long("crtc")
+                .help("Only adjust the CRTC with this zero-based index (X11)")
+                .value_name("N")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("day-temp")
+                .long("day-temp")
+                .help("Day temperature (default 6500)")
+                .value_name("K")
+                .value_parser(value_parser!(i32)),
+        )
+        .arg(
+            Arg::new("night-temp")
+                .long("night-temp")
+                .help("Night temperature (default 4500)")
+                .value_name("K")
+                .value_parser(value_parser!(i32)),
+        )
+        .arg(
+            Arg::new("day-mired")
+                .long("day-mired")
+                .help("Day temperature in mireds (1000000 / K)")
+                .value_name("MIRED")
+                .value_parser(parse_mired)
+                .conflicts_with("day-temp"),
+        )
+        .arg(
+            Arg::new("night-mired")
+                .long("night-mired")
+                .help("Night temperature in mireds (1000000 / K)")
+                .value_name("MIRED")
+                .value_parser(parse_mired)
+                .conflicts_with("night-temp"),
+        )
+        .arg(
+            Arg::new("invert")
+                .long("invert")
+                .help("Night temperature in daylight and day temperature at night (night shifts)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("gamma")
+                .long("gamma")
+                .help("Display gamma correction, one value or one per channel (e.g. 0.9:0.9:1.0)")
+                .value_name("R:G:B")
+                .value_parser(parse_gamma),
+        )
+        .arg(
+            Arg::new("accurate")
+                .long("accurate")
+                .help("Take the colors from the blackbody (Planckian locus) instead of xsct's fitted curves")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("brightness-floor")
+                .long("brightness-floor")
+                .help("Never apply a brightness below this (0.05 to 1, default 0.1)")
+                .value_name("B")
+                .value_parser(parse_brightness_floor),
+        )
+        .arg(
+            Arg::new("min-temp")
+                .long("min-temp")
+                .help("Never apply a temperature below this, whatever a profile, an override or a pause asks for")
+                .value_name("K")
+                .value_parser(parse_temp_limit),
+        )
+        .arg(
+            Arg::new("max-temp")
+                .long("max-temp")
+                .help("Never apply a temperature above this, whatever a profile, an override or a pause asks for")
+                .value_name("K")
+                .value_parser(parse_temp_limit),
+        )
+        .arg(
+            Arg::new("transition")
+                .long("transition")
+                .help("Smooth transitions, updated every few seconds")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("interval")
+                .long("interval")
+                .help("Recompute every SECS seconds instead of each minute (overrides --transition)")
+                .value_name("SECS")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("reassert")
+                .long("reassert")
+                .help("Every SECS seconds, apply the temperature again if another program reset the gamma")
+                .value_name("SECS")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("transition-minutes")
+                .long("transition-minutes")
+                .help("Length of the dawn/dusk ramps (default: up to noon)")
+                .value_name("N")
+                .value_parser(value_parser!(i32).range(0..=720)),
+        )
+        .arg(
+            Arg::new("dawn-duration")
+                .long("dawn-duration")
+                .help("Length of the morning ramp, instead of --transition-minutes")
+                .value_name("N")
+                .value_parser(value_parser!(i32).range(0..=720)),
+        )
+        .arg(
+            Arg::new("dusk-duration")
+                .long("dusk-duration")
+                .help("Length of the evening ramp, instead of --transition-minutes")
+                .value_name("N")
+                .value_parser(value_parser!(i32).range(0..=720)),
+        )
+        .arg(
+            Arg::new("min-transition-time")
+                .long("min-transition-time")
+                .help("Earliest time the morning ramp may start, whatever the sunrise")
+                .value_name("HH:MM")
+                .value_parser(parse_clock_time),
+        )
+        .arg(
+            Arg::new("max-transition-time")
+                .long("max-transition-time")
+                .help("Latest time the evening ramp may end, whatever the sunset")
+                .value_name("HH:MM")
+                .value_parser(parse_clock_time),
+        )
+        .arg(
+            Arg::new("offset")
+                .long("offset")
+                .help("Shift sunrise and sunset by N minutes (negative is earlier)")
+                .value_name("N")
+                .value_parser(value_parser!(i32).range(-180..=180))
+                .allow_negative_numbers(true),
+        )
+        .arg(
+            Arg::new("curve")
+                .long("curve")
+                .help("Shape of the dawn/dusk ramps: linear or cosine")
+                .value_name("SHAPE")
+                .value_parser(|s: &str| s.parse::<CurveShape>()),
+        )
+        .arg(
+            Arg::new("twilight")
+                .long("twilight")
+                .help("Sun position taken as sunrise/sunset: none, civil, nautical or astronomical")
+                .value_name("KIND")
+                .value_parser(|s: &str| s.parse::<Twilight>()),
+        )
+        .arg(
+            Arg::new("suntimes-file")
+                .long("suntimes-file")
+                .help("Take sunrise and sunset from a JSON file of {\"YYYY-MM-DD\": {\"sunrise\": \"HH:MM\", \"sunset\": \"HH:MM\"}}")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("fade-ms")
+                .long("fade-ms")
+                .help("Fade over MS milliseconds at startup and after resume")
+                .value_name("MS")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("perceptual-brightness")
+                .long("perceptual-brightness")
+                .help("Fade the brightness in even steps of perceived lightness instead of linearly")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print each update as a JSON object")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .help("Show a live status line redrawn in place")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["daemon", "json"]),
+        )
+        .arg(
+            Arg::new("notify")
+                .long("notify")
+                .help("Show a desktop notification when day, transition or night begins")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("backlight")
+                .long("backlight")
+                .help("Also dim the panel backlight, to NIGHT% at night and DAY% (default 100) by day")
+                .value_name("NIGHT[:DAY]")
+                .value_parser(backlight::parse_levels),
+        )
+        .arg(
+            Arg::new("backlight-device")
+                .long("backlight-device")
+                .help("Backlight device of /sys/class/backlight (default: firmware, then platform, then raw)")
+                .value_name("NAME")
+                .requires("backlight"),
+        )
+        .arg(
+            Arg::new("metrics-file")
+                .long("metrics-file")
+                .help("Write Prometheus metrics to PATH on each update (textfile collector)")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("no-apply")
+                .long("no-apply")
+                .help("Compute and log the temperature without changing the display")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("print-schedule")
+                .long("print-schedule")
+                .help("Print today's temperatures every 15 minutes and exit")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("list-outputs")
+                .long("list-outputs")
+                .help("List the connected X11 outputs with their CRTC, gamma ramp size and temperature, and exit")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dump-timezone-db")
+                .long("dump-timezone-db")
+                .help("Print the timezone tables and exit (for maintainers)")
+                .action(ArgAction::SetTrue)
+                .hide(true),
+        )
+        .arg(
+            Arg::new("oneshot")
+                .long("oneshot")
+                .help("Set the current temperature once and exit")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("restore-on-exit")
+                .long("restore-on-exit")
+                .help("Set the screens back to 6500K when stopped with ESC, stop or a signal")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("oneshot"),
+        )
+}
+
+// Latitude in degrees, -90 (south pole) to 90
+fn parse_latitude(value: &str) -> Result<f64, String> {
+    value.parse::<f64>().map_err(|e| e.to_string()).and_then(validate_latitude)
+}
+
+// Longitude in degrees, -180 (west) to 180 (east)
+fn parse_longitude(value: &str) -> Result<f64, String> {
+    value.parse::<f64>().map_err(|e| e.to_string()).and_then(validate_longitude)
+}
+
+// Mired values above 1 (below a million Kelvin), fractions allowed
+fn parse_mired(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(mired) if mired.is_finite() && mired >= 1.0 => Ok(mired),
+        _ => Err(format!("invalid mired value '{}'", value)),
+    }
+}
+
+// Lowest brightness, within BRIGHTNESS_FLOOR_RANGE
+fn parse_brightness_floor(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(floor) if BRIGHTNESS_FLOOR_RANGE.contains(&floor) => Ok(floor),
+        _ => Err(format!(
+            "invalid brightness floor '{}' (expected {} to {})",
+            value, BRIGHTNESS_FLOOR_RANGE.start(), BRIGHTNESS_FLOOR_RANGE.end()
+        )),
+    }
+}
+
+// Gamma exponents: "R:G:B", or a single value for the three channels,
+// each within GAMMA_RANGE
+fn parse_gamma(value: &str) -> Result<(f64, f64, f64), String> {
+    let exponents = value
+        .split(':')
+        .map(|part| part.trim().parse::<f64>().ok().filter(|g| GAMMA_RANGE.contains(g)))
+        .collect::<Option<Vec<f64>>>();
+    match exponents.as_deref() {
+        Some(&[g]) => Ok((g, g, g)),
+        Some(&[r, g, b]) => Ok((r, g, b)),
+        _ => Err(format!(
+            "invalid gamma '{}' (expected R:G:B or one value, each between {} and {})",
+            value, GAMMA_RANGE.start(), GAMMA_RANGE.end()
+        )),
+    }
+}
+
+// Time until the next minute starts, or the next smooth step if sooner.
+// With --interval, time until the next period. A timed pause ends on time.
+fn time_to_next_update(state: &AppState, last_step: Instant) -> Duration {
+    let until_update = scheduled_update_in(state, last_step);
+    match state.pause_until {
+        Some(until) if state.paused => {
+            until_update.min(until.duration_since(state.clock.system_time()).unwrap_or_default())
+        }
+        _ => until_update,
+    }
+}
+
+fn scheduled_update_in(state: &AppState, last_step: Instant) -> Duration {
+    if let Some(interval) = state.interval {
+        return interval.saturating_sub(last_step.elapsed());
+    }
+
+    let now = state.now();
+    let into_minute = Duration::new(now.second() as u64, now.nanosecond() % 1_000_000_000);
+    let until_minute = Duration::from_secs(60).saturating_sub(into_minute) + MINUTE_MARGIN;
+
+    if state.smooth {
+        let until_step = Duration::from_secs(SMOOTH_INTERVAL_SECS).saturating_sub(last_step.elapsed());
+        until_minute.min(until_step)
+    } else {
+        until_minute
+    }
+}
+
+// Pipe written by the TERM/INT/HUP handlers, read end non-blocking
+fn signal_wake_pipe() -> io::Result<UnixStream> {
+    let (reader, writer) = UnixStream::pair()?;
+    reader.set_nonblocking(true)?;
+    for signal in [SIGTERM, SIGINT, SIGHUP] {
+        signal_hook::low_level::pipe::register(signal, writer.try_clone()?)?;
+    }
+    Ok(reader)
+}
+
+fn drain(mut pipe: &UnixStream) {
+    let mut buffer = [0u8; 64];
+    while matches!(pipe.read(&mut buffer), Ok(n) if n > 0) {}
+}
+
+// Block until one of the descriptors is readable or the timeout expires
+fn wait_for_input(fds: &[RawFd], timeout: Duration) {
+    let mut pollfds: Vec<libc::pollfd> = fds
+        .iter()
+        .map(|&fd| libc::pollfd { fd, events: libc::POLLIN, revents: 0 })
+        .collect();
+    let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+    // EINTR and errors just end the wait early, the loop checks everything
+    unsafe {
+        libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, timeout_ms);
+    }
+}
+
+// Terminal in non-canonical, no-echo mode so single key presses can be read
+// without blocking. The original settings come back on drop, also when
+// unwinding from a panic.
+struct RawTerminal {
+    fd: RawFd,
+    original: Termios,
+}
+
+impl RawTerminal {
+    // None when stdin is not a terminal
+    fn enable() -> Option<RawTerminal> {
+        let fd = io::stdin().as_raw_fd();
+        let original = Termios::from_fd(fd).ok()?;
+
+        // Reads return immediately, with or without a key
+        let mut raw = original;
+        raw.c_lflag &= !(ICANON | ECHO);
+        raw.c_cc[VMIN] = 0;
+        raw.c_cc[VTIME] = 0;
+        tcsetattr(fd, TCSANOW, &raw).ok()?;
+
+        Some(RawTerminal { fd, original })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = tcsetattr(self.fd, TCSANOW, &self.original);
+    }
+}
+
+// Non-blocking ESC key check (stdin must be in raw mode, see RawTerminal)
+fn check_esc_key() -> bool {
+    let mut buffer = [0; 1];
+    io::stdin().read(&mut buffer).is_ok_and(|n| n > 0 && buffer[0] == ESC_KEY)
+}
+
+/// Check the options given to `colorwarm install-service` as `run` would
+/// parse them (exiting with the usage message when invalid), and that they
+/// suit a service running in the foreground
+pub fn check_service_options(options: &[String]) -> Result<(), String> {
+    let matches = command().get_matches_from(std::iter::once("colorwarm").chain(options.iter().map(String::as_str)));
+    let unsuitable = ["daemon", "oneshot", "print-schedule", "list-outputs", "watch"]
+        .into_iter()
+        .find(|&flag| matches.get_flag(flag));
+    match unsuitable {
+        Some("daemon") => Err("--daemon is not needed, systemd runs colorwarm in the background".to_string()),
+        Some(flag) => Err(format!("--{} does not make sense for a service", flag)),
+        None => Ok(()),
+    }
+}
+
+/// Run the automatic mode (interactive or daemon) with the given command line.
+/// Exits with a usage message if the options are invalid.
+pub fn run(args: &[String]) {
+    let matches = command().get_matches_from(args);
+    if matches.get_flag("dump-timezone-db") {
+        dump_timezone_db();
+        return;
+    }
+    if matches.get_flag("list-outputs") {
+        if let Err(e) = list_outputs(matches.get_one::<String>("display").map(String::as_str)) {
+            eprintln!("Error: {}", e);
+            exit(1);
+        }
+        return;
+    }
+    let config = match load_settings(&matches, None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            exit(1);
+        }
+    };
+
+    // --verbose shows the debug messages, unless --log-level says otherwise
+    let verbose = matches.get_flag("verbose") || config.verbose.unwrap_or(false);
+    logging::init(
+        matches.get_one::<LevelFilter>("log-level").copied(),
+        if verbose { LevelFilter::Debug } else { LevelFilter::Info },
+        !matches.get_flag("daemon"),
+    );
+
+    let (mut state, mut monthly_times) = match build_state(&matches, &config) {
+        Ok(built) => built,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            exit(1);
+        }
+    };
+    let daemon = state.daemon;
+
+    // Dry run: show today's curve without touching the display
+    if state.print_schedule {
+        print_schedule(&state, &monthly_times);
+        return;
+    }
+
+    // Fail before detaching when there is no display at all (headless, SSH)
+    if !state.no_apply && state.display.is_none() && !has_display() {
+        eprintln!("Error: no X11 display available (neither DISPLAY nor WAYLAND_DISPLAY is set)");
+        exit(1);
+    }
+
+    // One-shot mode: apply the current target and exit (cron, .xprofile)
+    if state.oneshot {
+        let backend = select_backend(&state);
+        backend.set_output_offsets(&state.output_offsets);
+        backend.set_excluded_outputs(&state.excluded_outputs);
+        backend.set_gamma(state.gamma);
+        backend.set_white_point(state.white_point);
+        backend.set_temperature_limits(state.temp_limits);
+        let target = state.limited(current_target(&state, &monthly_times));
+        if let Err(e) = backend.set_temperature(target) {
+            eprintln!("Error setting temperature: {}", e);
+            exit(1);
+        }
+        if state.verbose {
+            println!("[{}] {}K", format_time(state.clock.local_time(state.zone)), target.temp);
+        }
+        return;
+    }
+
+    // If daemon mode, detach from terminal
+    if daemon {
+        let (stdout, stderr) = match (create_private_file(&log_file()), create_private_file(&err_file())) {
+            (Ok(stdout), Ok(stderr)) => (stdout, stderr),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("Error: cannot create the daemon log files: {}", e);
+                exit(1);
+            }
+        };
+
+        // Private umask so the pid file and the control socket are 0600.
+        // Run from / so the daemon does not keep a mounted filesystem busy.
+        let daemonize = Daemonize::new()
+            .pid_file(&state.pid_file)
+            .working_directory("/")
+            .umask(0o077)
+            .stdout(stdout)
+            .stderr(stderr);
+
+        match daemonize.start() {
+            Ok(()) => {
+                // Daemon lancé avec succès
+                info!("ColorWarm démarré en mode daemon.");
+            }
+            Err(e) => {
+                eprintln!("Erreur lors du démarrage du daemon: {}", e);
+                exit(1);
+            }
+        }
+    }
+
+    println!("ColorWarm v{} - Worldwide Timezone Support", env!("CARGO_PKG_VERSION"));
+    println!("2025 - Philippe TEMESI");
+    println!("https://www.tems.be");
+    println!("Timezone: {}", state.timezone);
+    println!("Location: {}", state.location_name);
+    println!("Integrated xsct functionality included");
+    println!();
+    if !daemon {
+        println!("Press ESC to exit");
+        println!("------------------------------------------");
+    }
+    io::stdout().flush().unwrap();
+
+    // Stop cleanly on SIGTERM/SIGINT
+    let terminate = Arc::new(AtomicBool::new(false));
+    for signal in [SIGTERM, SIGINT] {
+        if let Err(e) = signal_hook::flag::register(signal, Arc::clone(&terminate)) {
+            warn!("Cannot install handler for signal {}: {}", signal, e);
+        }
+    }
+
+    // Reload configuration on SIGHUP
+    let reload = Arc::new(AtomicBool::new(false));
+    if let Err(e) = signal_hook::flag::register(SIGHUP, Arc::clone(&reload)) {
+        warn!("Cannot install handler for signal {}: {}", SIGHUP, e);
+    }
+
+    // Signals also write to this pipe to interrupt the daemon's long sleep
+    let wake_pipe = match signal_wake_pipe() {
+        Ok(pipe) => Some(pipe),
+        Err(e) => {
+            warn!("Cannot create signal pipe: {}", e);
+            None
+        }
+    };
+
+    // Select X11 or Wayland
+    let backend = select_backend(&state);
+    backend.set_output_offsets(&state.output_offsets);
+    backend.set_excluded_outputs(&state.excluded_outputs);
+    backend.set_gamma(state.gamma);
+    backend.set_white_point(state.white_point);
+    backend.set_temperature_limits(state.temp_limits);
+    if log_enabled!(Level::Debug) {
+        match backend.get_temperature() {
+            Ok(current) => debug!("Using {} backend (current: {}K, brightness {:.2})",
+                                  backend.name(), current.temp, current.brightness),
+            Err(e) => debug!("Using {} backend (current temperature unknown: {})", backend.name(), e),
+        }
+    }
+
+    // Control socket (daemon only)
+    let control_socket = if daemon {
+        match control::ControlSocket::bind() {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                warn!("Control socket disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Start from the temperature of the previous run rather than whatever
+    // the session reset the gamma to, so the first fade is continuous
+    if !state.no_apply {
+        if let Some(last) = last_temp::load() {
+            if let Err(e) = backend.set_temperature(state.limited(last)) {
+                warn!("Error restoring the last temperature: {}", e);
+            }
+        }
+    }
+
+    // Do first update immediately, with an override file already in place
+    poll_override_file(&mut state);
+    fade_to_target(&state, &monthly_times, backend.as_ref());
+    manage_brightness_cycle(&state, &monthly_times, backend.as_ref());
+
+    // Get current minute
+    let mut last_minute = state.clock.minute(state.zone);
+    let mut last_step = Instant::now();
+    let watchdog = systemd::watchdog_period();
+    let mut last_ping = Instant::now();
+    let mut last_reassert = Instant::now();
+    let mut last_gpsd = Instant::now();
+    let mut last_override_poll = Instant::now();
+    let mut last_day = state.now().date();
+
+    // Raw mode for the ESC key, restored when leaving run()
+    let terminal = if daemon { None } else { RawTerminal::enable() };
+    let mut last_drawn = None;
+
+    // Main loop
+    loop {
+        // Check ESC key or termination signal
+        if terminate.load(Ordering::Relaxed) || (terminal.is_some() && check_esc_key()) {
+            if state.watch {
+                // Leave the status line on screen
+                println!();
+            }
+            shutdown(&state, backend.as_ref());
+            break;
+        }
+
+        // Computed again on every pass: a control command, a reload, the end
+        // of a pause or a new position may have moved it
+        let boundary = next_boundary(&state, &monthly_times);
+
+        // Live status line, redrawn once per second
+        if state.watch {
+            let second = state.now().and_utc().timestamp();
+            if last_drawn != Some(second) {
+                last_drawn = Some(second);
+                draw_watch_line(&state, &monthly_times);
+            }
+        }
+
+        // Keep the systemd watchdog (WatchdogSec=) from restarting us
+        if let Some(period) = watchdog {
+            if last_ping.elapsed() >= period {
+                systemd::watchdog_ping();
+                last_ping = Instant::now();
+            }
+        }
+
+        // Wait 100ms for the ESC key, otherwise sleep until the next update
+        // unless a signal, a control command or a display event comes first
+        // (wall clock, as the monotonic clock stops during suspend)
+        let timeout = if daemon {
+            let next_update = time_to_next_update(&state, last_step);
+            let next_ping = watchdog.map(|period| period.saturating_sub(last_ping.elapsed()));
+            let next_reassert = state.reassert.map(|period| period.saturating_sub(last_reassert.elapsed()));
+            let next_gpsd = state.gpsd.as_ref().map(|_| GPSD_REFRESH.saturating_sub(last_gpsd.elapsed()));
+            let next_boundary = boundary.map(|at| at.duration_since(state.clock.system_time()).unwrap_or_default());
+            let next_override_poll = OVERRIDE_POLL.saturating_sub(last_override_poll.elapsed());
+            [backend.reconnect_in(), next_ping, next_reassert, next_gpsd, next_boundary, Some(next_override_poll)]
+                .into_iter()
+                .flatten()
+                .fold(next_update, Duration::min)
+        } else {
+            Duration::from_millis(100)
+        };
+        let tick = SystemTime::now();
+        if daemon {
+            let fds: Vec<RawFd> = [
+                wake_pipe.as_ref().map(|pipe| pipe.as_raw_fd()),
+                control_socket.as_ref().map(|socket| socket.as_raw_fd()),
+                backend.event_fd(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            wait_for_input(&fds, timeout);
+            if let Some(pipe) = &wake_pipe {
+                drain(pipe);
+            }
+        } else {
+            sleep(timeout);
+        }
+
+        // The loop stalled much longer than asked: the machine was suspended
+        if tick.elapsed().is_ok_and(|gap| gap >= timeout + Duration::from_secs(SUSPEND_GAP_SECS)) {
+            fade_to_target(&state, &monthly_times, backend.as_ref());
+        }
+
+        // Answer control socket commands
+        if let Some(socket) = &control_socket {
+            socket.poll(&matches, &mut state, &mut monthly_times, backend.as_ref());
+        }
+
+        // Monitor plugged or reconfigured: apply to the new layout now
+        if backend.display_changed() {
+            manage_brightness_cycle(&state, &monthly_times, backend.as_ref());
+        }
+
+        // Apply a configuration reload immediately
+        if reload.swap(false, Ordering::Relaxed) {
+            reload_state(&matches, &mut state, &mut monthly_times);
+            backend.set_output_offsets(&state.output_offsets);
+            manage_brightness_cycle(&state, &monthly_times, backend.as_ref());
+        }
+
+        // Override file written or removed by a script
+        if last_override_poll.elapsed() >= OVERRIDE_POLL {
+            last_override_poll = Instant::now();
+            if poll_override_file(&mut state) {
+                manage_brightness_cycle(&state, &monthly_times, backend.as_ref());
+            }
+        }
+
+        // End of a `ctl pause SECONDS`
+        if state.pause_expired() {
+            state.resume();
+            info!("Pause over, resuming");
+            manage_brightness_cycle(&state, &monthly_times, backend.as_ref());
+        }
+
+        // Date rollover: summary of the new day in the log
+        let today = state.now().date();
+        if today != last_day {
+            last_day = today;
+            log_daily_summary(&state, &monthly_times);
+        }
+
+        // Follow the vehicle: new sun times once the position changed
+        if state.gpsd.is_some() && last_gpsd.elapsed() >= GPSD_REFRESH {
+            refresh_gpsd_position(&mut state, &mut monthly_times);
+            last_gpsd = Instant::now();
+        }
+
+        // Take the gamma back from programs that reset it
+        if let Some(period) = state.reassert {
+            if last_reassert.elapsed() >= period {
+                reassert_temperature(&state, &monthly_times, backend.as_ref());
+                last_reassert = Instant::now();
+            }
+        }
+
+        // Get current minute
+        let current_minute = state.clock.minute(state.zone);
+
+        // Timer driven updates (--interval), plus one right at each sunrise,
+        // sunset and end of a ramp. Otherwise update when the minute changes
+        // (sunrise and sunset are whole minutes) and between minutes in
+        // smooth transition mode.
+        if let Some(interval) = state.interval {
+            let at_boundary = boundary.is_some_and(|at| state.clock.system_time() >= at);
+            if last_step.elapsed() >= interval || at_boundary {
+                manage_brightness_cycle(&state, &monthly_times, backend.as_ref());
+                last_step = Instant::now();
+            }
+        } else if current_minute != last_minute {
+            last_minute = current_minute;
+            manage_brightness_cycle(&state, &monthly_times, backend.as_ref());
+            last_step = Instant::now();
+        } else if state.smooth && last_step.elapsed() >= Duration::from_secs(SMOOTH_INTERVAL_SECS) {
+            apply_smooth_step(&state, &monthly_times, backend.as_ref());
+            last_step = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::FixedClock;
+    use std::cell::RefCell;
+
+    // Records every temperature set, reads back the last one
+    #[derive(Default)]
+    struct MockBackend {
+        applied: RefCell<Vec<TempStatus>>,
+        offset: Cell<i32>, // Of its one output, named "mock"
+    }
+
+    impl Backend for MockBackend {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn get_temperature(&self) -> Result<TempStatus, Box<dyn std::error::Error>> {
+            self.applied.borrow().last().copied().ok_or_else(|| "nothing applied yet".into())
+        }
+
+        fn set_temperature(&self, temp: TempStatus) -> Result<(), Box<dyn std::error::Error>> {
+            self.applied.borrow_mut().push(TempStatus { temp: temp.temp + self.offset.get(), ..temp });
+            Ok(())
+        }
+
+        fn set_output_offsets(&self, offsets: &HashMap<String, i32>) {
+            self.offset.set(offsets.get("mock").copied().unwrap_or(0));
+        }
+    }
+
+    // Brussels with one-hour ramps; --no-apply keeps the state file untouched.
+    // The times come from the monthly table, as the ones computed for
+    // coordinates follow the timezone of the machine running the tests.
+    fn brussels() -> (AppState, MonthlyTimes) {
+        let args = command().get_matches_from([
+            "colorwarm", "--lat", "50.85", "--lon", "4.35", "--transition-minutes", "60", "--no-apply",
+        ]);
+        let (state, _) = build_state(&args, &Config::default()).unwrap();
+        (state, MonthlyTimes::new_for_timezone("Europe/Brussels"))
+    }
+
+    fn summer_day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 6, 21).unwrap()
+    }
+
+    // `state` with its clock stopped at `now`
+    fn at(state: AppState, now: NaiveDateTime) -> AppState {
+        AppState { clock: Rc::new(FixedClock::new(now)), ..state }
+    }
+
+    // One cycle every `step` minutes over the day, with the kelvin applied at each
+    fn simulate_day(state: AppState, monthly_times: &MonthlyTimes, step: usize) -> Vec<(i32, i32)> {
+        let backend = MockBackend::default();
+        let clock = Rc::new(FixedClock::new(summer_day().and_hms_opt(0, 0, 0).unwrap()));
+        let state = AppState { clock: clock.clone(), ..state };
+        let minutes: Vec<i32> = (0..MINUTES_PER_DAY).step_by(step).collect();
+        for &minute in &minutes {
+            clock.set(summer_day().and_hms_opt(minute as u32 / 60, minute as u32 % 60, 0).unwrap());
+            manage_brightness_cycle(&state, monthly_times, &backend);
+        }
+        let applied = backend.applied.borrow();
+        minutes.into_iter().zip(applied.iter().map(|temp| temp.temp)).collect()
+    }
+
+    #[test]
+    fn simulated_day_follows_the_sun() {
+        let (state, monthly_times) = brussels();
+        let (sunrise, sunset, _) = schedule_on(&state, &monthly_times, summer_day());
+        let day = simulate_day(state, &monthly_times, 5);
+        assert_eq!(day.len(), (MINUTES_PER_DAY / 5) as usize);
+
+        for &(minute, kelvin) in &day {
+            if minute < sunrise || minute >= sunset {
+                assert_eq!(kelvin, TEMPERATURE_NIGHT, "night at {}", format_time(minute));
+            } else if minute >= sunrise + 60 && minute <= sunset - 60 {
+                assert_eq!(kelvin, TEMPERATURE_NORM, "plateau at {}", format_time(minute));
+            }
+        }
+
+        let ramp = |from: i32, to: i32| -> Vec<i32> {
+            day.iter().filter(|&&(minute, _)| minute >= from && minute <= to).map(|&(_, k)| k).collect()
+        };
+        let sunrise_ramp = ramp(sunrise, sunrise + 60);
+        let sunset_ramp = ramp(sunset - 60, sunset - 1);
+        assert!(sunrise_ramp.windows(2).all(|w| w[0] <= w[1]), "sunrise ramp: {:?}", sunrise_ramp);
+        assert!(sunset_ramp.windows(2).all(|w| w[0] >= w[1]), "sunset ramp: {:?}", sunset_ramp);
+        assert!(sunrise_ramp.iter().any(|&k| k > TEMPERATURE_NIGHT && k < TEMPERATURE_NORM));
+        assert!(sunset_ramp.iter().any(|&k| k > TEMPERATURE_NIGHT && k < TEMPERATURE_NORM));
+    }
+
+    #[test]
+    fn brightness_never_below_the_floor() {
+        let (state, _) = brussels();
+        let state = AppState { brightness_floor: 0.3, ..state };
+        let backend = MockBackend::default();
+        set_temperature_logged(&state, &backend, TempStatus { temp: 4500, brightness: 0.0 });
+        assert_eq!(backend.get_temperature().unwrap().brightness, 0.3);
+        set_temperature_logged(&state, &backend, TempStatus { temp: 4500, brightness: 0.8 });
+        assert_eq!(backend.get_temperature().unwrap().brightness, 0.8);
+    }
+
+    #[test]
+    fn override_file_within_the_temperature_limits() {
+        let (state, monthly_times) = brussels();
+        let temp_limits = TempLimits::new(Some(3000), Some(6000)).unwrap();
+        let state = AppState { temp_limits, file_override: Some(1200), ..state };
+        let state = at(state, summer_day().and_hms_opt(12, 0, 0).unwrap());
+        let backend = MockBackend::default();
+        manage_brightness_cycle(&state, &monthly_times, &backend);
+        assert_eq!(backend.get_temperature().unwrap().temp, 3000);
+        let state = AppState { file_override: None, ..state };
+        manage_brightness_cycle(&state, &monthly_times, &backend);
+        assert_eq!(backend.get_temperature().unwrap().temp, 6000);
+    }
+
+    #[test]
+    fn restore_on_exit_within_the_temperature_limits() {
+        let (state, _) = brussels();
+        let temp_limits = TempLimits::new(None, Some(5000)).unwrap();
+        let state = AppState { temp_limits, restore_on_exit: true, no_apply: false, ..state };
+        let backend = MockBackend::default();
+        shutdown(&state, &backend);
+        assert_eq!(backend.get_temperature().unwrap(), TempStatus { temp: 5000, brightness: 1.0 });
+    }
+
+    #[test]
+    fn restore_on_exit_leaves_out_the_output_offsets() {
+        let (state, monthly_times) = brussels();
+        let output_offsets = HashMap::from([("mock".to_string(), 500)]);
+        let state = AppState { output_offsets, restore_on_exit: true, no_apply: false, ..state };
+        let state = at(state, summer_day().and_hms_opt(0, 0, 0).unwrap());
+        let backend = MockBackend::default();
+        backend.set_output_offsets(&state.output_offsets);
+        shutdown(&state, &backend);
+        assert_eq!(backend.get_temperature().unwrap().temp, TEMPERATURE_NORM);
+        // Back for the next update
+        manage_brightness_cycle(&state, &monthly_times, &backend);
+        assert_eq!(backend.get_temperature().unwrap().temp, TEMPERATURE_NIGHT + 500);
+    }
+
+    #[test]
+    fn metrics_report_the_limited_temperature() {
+        let (state, monthly_times) = brussels();
+        let path = std::env::temp_dir().join(format!("colorwarm-metrics-{}.prom", std::process::id()));
+        let temp_limits = TempLimits::new(None, Some(5000)).unwrap();
+        let state = AppState { temp_limits, metrics_file: Some(path.clone()), ..state };
+        let state = at(state, summer_day().and_hms_opt(12, 0, 0).unwrap());
+        manage_brightness_cycle(&state, &monthly_times, &MockBackend::default());
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("\ncolorwarm_current_kelvin 5000\n"), "{}", text);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn linear_ramp_is_halfway_after_half_the_transition() {
+        let (state, monthly_times) = brussels();
+        let (sunrise, _, _) = schedule_on(&state, &monthly_times, summer_day());
+        let backend = MockBackend::default();
+        let minute = (sunrise + 30) as u32;
+        let state = at(state, summer_day().and_hms_opt(minute / 60, minute % 60, 0).unwrap());
+        manage_brightness_cycle(&state, &monthly_times, &backend);
+        assert_eq!(backend.get_temperature().unwrap().temp, (TEMPERATURE_NIGHT + TEMPERATURE_NORM) / 2);
+    }
+
+    #[test]
+    fn winter_morning_in_paris() {
+        let args = command().get_matches_from([
+            "colorwarm", "--lat", "48.86", "--lon", "2.35", "--transition-minutes", "60", "--no-apply",
+        ]);
+        let (state, _) = build_state(&args, &Config::default()).unwrap();
+        let monthly_times = MonthlyTimes::new_for_timezone("Europe/Paris");
+        let january_15 = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+        let backend = MockBackend::default();
+
+        let state = at(state, january_15.and_hms_opt(5, 30, 0).unwrap());
+        manage_brightness_cycle(&state, &monthly_times, &backend);
+        assert_eq!(backend.get_temperature().unwrap().temp, TEMPERATURE_NIGHT);
+        let state = at(state, january_15.and_hms_opt(13, 0, 0).unwrap());
+        manage_brightness_cycle(&state, &monthly_times, &backend);
+        assert_eq!(backend.get_temperature().unwrap().temp, TEMPERATURE_NORM);
+        assert_eq!(current_target(&state, &monthly_times).temp, TEMPERATURE_NORM);
+    }
+
+    #[test]
+    fn forced_temperature_holds_all_day() {
+        let (mut state, monthly_times) = brussels();
+        state.forced_temp = Some(3000);
+        assert!(simulate_day(state, &monthly_times, 60).iter().all(|&(_, kelvin)| kelvin == 3000));
+    }
+
+    #[test]
+    fn paused_leaves_the_gamma_alone() {
+        let (mut state, monthly_times) = brussels();
+        state.paused = true;
+        let state = at(state, summer_day().and_hms_opt(12, 0, 0).unwrap());
+        let backend = MockBackend::default();
+        manage_brightness_cycle(&state, &monthly_times, &backend);
+        assert!(backend.applied.borrow().is_empty());
+    }
+
+    #[test]
+    fn timed_pause_ends_on_the_clock() {
+        let (state, _) = brussels();
+        let noon = summer_day().and_hms_opt(12, 0, 0).unwrap();
+        let clock = Rc::new(FixedClock::new(noon));
+        let mut state = AppState { clock: clock.clone(), ..state };
+        state.paused = true;
+        state.pause_until = Some(clock.system_time() + Duration::from_secs(3600));
+        clock.set(summer_day().and_hms_opt(12, 59, 59).unwrap());
+        assert!(!state.pause_expired());
+        clock.set(summer_day().and_hms_opt(13, 0, 0).unwrap());
+        assert!(state.pause_expired());
+    }
+
+    #[test]
+    fn boundary_returns_after_a_pause() {
+        let (mut state, monthly_times) = brussels();
+        state.interval = Some(Duration::from_secs(3600));
+        let mut state = at(state, summer_day().and_hms_opt(12, 0, 0).unwrap());
+        let boundary = next_boundary(&state, &monthly_times);
+        assert!(boundary.is_some());
+        state.paused = true;
+        assert_eq!(next_boundary(&state, &monthly_times), None);
+        state.resume();
+        assert_eq!(next_boundary(&state, &monthly_times), boundary);
+    }
+
+    #[test]
+    fn pause_survives_a_reload() {
+        let args = command().get_matches_from(["colorwarm", "--lat", "50.85", "--lon", "4.35", "--no-apply"]);
+        let (mut state, mut monthly_times) = build_state(&args, &Config::default()).unwrap();
+        let until = SystemTime::now() + Duration::from_secs(600);
+        state.paused = true;
+        state.pause_until = Some(until);
+        reload_state(&args, &mut state, &mut monthly_times);
+        assert!(state.paused);
+        assert_eq!(state.pause_until, Some(until));
+        let backend = MockBackend::default();
+        manage_brightness_cycle(&state, &monthly_times, &backend);
+        assert!(backend.applied.borrow().is_empty());
+    }
+
+    #[test]
+    fn watch_line_shows_the_afternoon() {
+        let (state, monthly_times) = brussels();
+        let line = watch_line(&state, &monthly_times, summer_day().and_hms_opt(15, 30, 0).unwrap());
+        assert!(line.starts_with("15:30:00  6500K  "), "{}", line);
+        assert!(line.contains("[############"), "{}", line);
+        assert!(line.contains("--] "), "{}", line);
+        assert!(line.contains("next: transition at "), "{}", line);
+    }
+}
@@ -0,0 +1,155 @@
+// Display backends: X11 RandR (x11.rs) and Wayland wlr-gamma-control (wayland.rs)
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::env;
+use std::os::unix::io::RawFd;
+use std::process::exit;
+use std::time::Duration;
+#[cfg(feature = "wayland")]
+use log::{info, warn};
+
+#[cfg(feature = "wayland")]
+use crate::wayland;
+#[cfg(feature = "x11")]
+pub use crate::x11::X11Backend;
+use crate::xsct::{TempLimits, TempStatus, WhitePoint, TEMPERATURE_NORM};
+
+/// Display server abstraction used by the automatic mode
+pub trait Backend {
+    fn name(&self) -> &'static str;
+    fn get_temperature(&self) -> Result<TempStatus, Box<dyn std::error::Error>>;
+    fn set_temperature(&self, temp: TempStatus) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Temperature offsets (Kelvin) per output name, added to the requested
+    /// temperature. Backends that cannot tell outputs apart ignore them.
+    fn set_output_offsets(&self, _offsets: &HashMap<String, i32>) {}
+
+    /// Outputs whose gamma is never read nor written (--exclude).
+    /// Backends that cannot tell outputs apart ignore them.
+    fn set_excluded_outputs(&self, _names: &[String]) {}
+
+    /// Display gamma exponents (red, green, blue) applied to the ramps,
+    /// `GAMMA_LINEAR` until set
+    fn set_gamma(&self, _exponents: (f64, f64, f64)) {}
+
+    /// How temperatures become channel multipliers, `WhitePoint::Empirical`
+    /// until set
+    fn set_white_point(&self, _white_point: WhitePoint) {}
+
+    /// Range the temperature of every output is kept within, per-output
+    /// offsets included. Callers clamp the requested temperature themselves.
+    fn set_temperature_limits(&self, _limits: TempLimits) {}
+
+    /// True if monitors were plugged, unplugged or reconfigured since the
+    /// last call, so the temperature should be applied again right away
+    fn display_changed(&self) -> bool {
+        false
+    }
+
+    /// File descriptor that becomes readable when `display_changed` may have
+    /// something to report, so the main loop can sleep on it
+    fn event_fd(&self) -> Option<RawFd> {
+        None
+    }
+
+    /// While the display server is away, how long until the next reconnection
+    /// attempt (made by `display_changed`)
+    fn reconnect_in(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Screen and CRTC the X11 backend is limited to (--screen, --crtc).
+/// Zero-based indexes as in xsct; None selects all of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CrtcSelection {
+    pub screen: Option<usize>,
+    pub crtc: Option<usize>,
+}
+
+/// Backend that changes nothing and only remembers the last temperature
+/// requested (--no-apply)
+pub struct DryRunBackend {
+    current: Cell<TempStatus>,
+}
+
+impl DryRunBackend {
+    pub fn new() -> Self {
+        DryRunBackend {
+            current: Cell::new(TempStatus { temp: TEMPERATURE_NORM, brightness: 1.0 }),
+        }
+    }
+}
+
+impl Default for DryRunBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for DryRunBackend {
+    fn name(&self) -> &'static str {
+        "dry run"
+    }
+
+    fn get_temperature(&self) -> Result<TempStatus, Box<dyn std::error::Error>> {
+        Ok(self.current.get())
+    }
+
+    fn set_temperature(&self, temp: TempStatus) -> Result<(), Box<dyn std::error::Error>> {
+        self.current.set(temp);
+        Ok(())
+    }
+}
+
+/// True if the session has a Wayland or X11 display to talk to
+pub fn has_display() -> bool {
+    ["WAYLAND_DISPLAY", "DISPLAY"]
+        .iter()
+        .any(|name| env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+/// Choose the backend from the session type: Wayland (wlr-gamma-control) when
+/// WAYLAND_DISPLAY is set and the compositor supports it, X11 otherwise.
+/// An X display name always selects X11. Backends left out of the build
+/// (the x11 and wayland features) are never chosen.
+pub fn detect_backend(selection: CrtcSelection, display: Option<&str>) -> Box<dyn Backend> {
+    #[cfg(feature = "wayland")]
+    if display.is_none() && env::var_os("WAYLAND_DISPLAY").is_some() {
+        match wayland::WaylandBackend::connect() {
+            Ok(backend) => {
+                if selection != CrtcSelection::default() {
+                    warn!("--screen and --crtc only apply to X11, ignored on Wayland");
+                }
+                return Box::new(backend);
+            }
+            Err(e) => {
+                if !cfg!(feature = "x11") || env::var_os("DISPLAY").is_none() {
+                    eprintln!("Error: Wayland gamma control unavailable: {}", e);
+                    exit(1);
+                }
+                info!("Wayland gamma control unavailable ({}), falling back to X11", e);
+            }
+        }
+    }
+
+    // Check the display (and the selection) once: without one the main loop
+    // would only log errors
+    #[cfg(feature = "x11")]
+    {
+        let backend = X11Backend::with_selection(selection).on_display(display);
+        if let Err(e) = backend.check() {
+            eprintln!("Error: {}", e);
+            exit(1);
+        }
+        Box::new(backend)
+    }
+
+    #[cfg(not(feature = "x11"))]
+    {
+        let _ = (selection, display);
+        eprintln!("Error: no Wayland display available (DISPLAY is not used: built without X11 support)");
+        exit(1);
+    }
+}
@@ -0,0 +1,154 @@
+// Panel backlight through /sys/class/backlight (--backlight), dimmed along
+// the same curve as the color temperature. Writing the brightness file
+// needs a udev rule (or the video group) on most distributions.
+
+use std::cell::Cell;
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+pub const SYSFS_DIR: &str = "/sys/class/backlight";
+const LEVEL_RANGE: RangeInclusive<u32> = 5..=100; // Percent; never off
+// Device types by preference, as documented for the sysfs interface
+const TYPES: [&str; 3] = ["firmware", "platform", "raw"];
+
+/// Backlight levels in percent of the maximum: at night and in daylight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Levels {
+    pub night: u32,
+    pub day: u32,
+}
+
+impl Levels {
+    /// Level at a position between night (0) and day (1), in percent
+    pub fn at(self, fraction: f64) -> f64 {
+        let fraction = fraction.clamp(0.0, 1.0);
+        self.night as f64 + (self.day as f64 - self.night as f64) * fraction
+    }
+}
+
+/// Parse "NIGHT" or "NIGHT:DAY" percentages, the day level defaulting to 100
+pub fn parse_levels(value: &str) -> Result<Levels, String> {
+    let invalid = || {
+        format!(
+            "invalid backlight '{}' (expected NIGHT or NIGHT:DAY, percentages from {} to {})",
+            value, LEVEL_RANGE.start(), LEVEL_RANGE.end()
+        )
+    };
+    let percent = |part: &str| part.trim().parse::<u32>().ok().filter(|p| LEVEL_RANGE.contains(p));
+    match value.split_once(':') {
+        None => Ok(Levels { night: percent(value).ok_or_else(invalid)?, day: 100 }),
+        Some((night, day)) => Ok(Levels {
+            night: percent(night).ok_or_else(invalid)?,
+            day: percent(day).ok_or_else(invalid)?,
+        }),
+    }
+}
+
+/// One backlight device, written only when the level changes so that a
+/// manual adjustment holds until the curve moves again
+#[derive(Debug)]
+pub struct Backlight {
+    device: PathBuf,
+    max: u32,
+    written: Cell<Option<u32>>,
+}
+
+impl Backlight {
+    /// The device of `dir` named `name`, else the preferred one by type
+    pub fn open(dir: &Path, name: Option<&str>) -> Result<Self, String> {
+        let device = match name {
+            Some(name) => dir.join(name),
+            None => preferred_device(dir)?,
+        };
+        let max_file = device.join("max_brightness");
+        let max = fs::read_to_string(&max_file)
+            .map_err(|e| format!("cannot read {}: {}", max_file.display(), e))?
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|&max| max > 0)
+            .ok_or_else(|| format!("invalid {}", max_file.display()))?;
+        Ok(Backlight { device, max, written: Cell::new(None) })
+    }
+
+    /// Device name, e.g. intel_backlight
+    pub fn name(&self) -> String {
+        self.device.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+
+    /// Set the backlight to `percent` of its maximum. Returns false when it
+    /// was already there.
+    pub fn set_percent(&self, percent: f64) -> io::Result<bool> {
+        let raw = ((self.max as f64 * percent / 100.0).round() as u32).clamp(1, self.max);
+        if self.written.get() == Some(raw) {
+            return Ok(false);
+        }
+        let path = self.device.join("brightness");
+        fs::write(&path, raw.to_string()).map_err(|e| {
+            let hint = match e.kind() {
+                io::ErrorKind::PermissionDenied => " (needs a udev rule giving write access, see README)",
+                _ => "",
+            };
+            io::Error::new(e.kind(), format!("cannot write {}: {}{}", path.display(), e, hint))
+        })?;
+        self.written.set(Some(raw));
+        Ok(true)
+    }
+}
+
+// First device of the most preferred type
+fn preferred_device(dir: &Path) -> Result<PathBuf, String> {
+    let mut devices: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("cannot list {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    devices.sort();
+    let rank = |device: &PathBuf| {
+        let kind = fs::read_to_string(device.join("type")).unwrap_or_default();
+        TYPES.iter().position(|&t| t == kind.trim()).unwrap_or(TYPES.len())
+    };
+    devices
+        .into_iter()
+        .min_by_key(rank)
+        .ok_or_else(|| format!("no backlight device in {}", dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels() {
+        assert_eq!(parse_levels("40"), Ok(Levels { night: 40, day: 100 }));
+        assert_eq!(parse_levels("30:80"), Ok(Levels { night: 30, day: 80 }));
+        for bad in ["0", "101", "abc", "40:", "40:0"] {
+            assert!(parse_levels(bad).is_err(), "{}", bad);
+        }
+        let levels = Levels { night: 30, day: 80 };
+        assert_eq!(levels.at(0.0), 30.0);
+        assert_eq!(levels.at(0.5), 55.0);
+        assert_eq!(levels.at(2.0), 80.0);
+    }
+
+    #[test]
+    fn sysfs_device() {
+        let dir = std::env::temp_dir().join(format!("colorwarm-backlight-{}", std::process::id()));
+        for (name, kind) in [("acpi_video0", "firmware"), ("intel_backlight", "raw")] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            fs::write(dir.join(name).join("type"), format!("{}\n", kind)).unwrap();
+            fs::write(dir.join(name).join("max_brightness"), "1000\n").unwrap();
+        }
+
+        let backlight = Backlight::open(&dir, None).unwrap();
+        assert_eq!(backlight.name(), "acpi_video0");
+        let backlight = Backlight::open(&dir, Some("intel_backlight")).unwrap();
+        assert!(backlight.set_percent(40.0).unwrap());
+        assert!(!backlight.set_percent(40.0).unwrap());
+        assert_eq!(fs::read_to_string(dir.join("intel_backlight").join("brightness")).unwrap(), "400");
+        assert!(Backlight::open(&dir, Some("missing")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,332 @@
+// Config file and per-user paths
+
+use chrono::Weekday;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::schedule::{parse_clock_time, CurveShape, MINUTES_PER_DAY};
+use crate::sun::Twilight;
+use crate::xsct::TEMPERATURE_ZERO;
+
+/// Settings read from ~/.config/colorwarm/config.toml (all keys optional)
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub day_temp: Option<i32>,
+    pub night_temp: Option<i32>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub transition_minutes: Option<i32>,
+    /// Morning and evening ramp lengths in minutes, instead of transition_minutes
+    pub dawn_duration: Option<i32>,
+    pub dusk_duration: Option<i32>,
+    /// Earliest sunrise and latest sunset, as "HH:MM"
+    pub min_transition_time: Option<String>,
+    pub max_transition_time: Option<String>,
+    pub offset_minutes: Option<i32>,
+    pub curve: Option<CurveShape>,
+    pub twilight: Option<Twilight>,
+    pub brightness: Option<f64>,
+    pub verbose: Option<bool>,
+    /// [outputs] section: RandR output name -> temperature offset in Kelvin
+    #[serde(default)]
+    pub outputs: HashMap<String, i32>,
+    /// [minute of the day, kelvin] pairs replacing the sun-based curve
+    #[serde(default)]
+    pub stops: Vec<(i32, i32)>,
+    /// [schedule.<weekday>] sections: per-weekday changes to the schedule
+    #[serde(default)]
+    pub schedule: HashMap<String, WeekdaySchedule>,
+    /// Profile used unless --profile names another one
+    pub profile: Option<String>,
+    /// [profiles.<name>] sections: named presets
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// Schedule changes for one day of the week (all keys optional)
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeekdaySchedule {
+    /// Minutes added to sunrise and sunset, on top of offset_minutes
+    pub offset: Option<i32>,
+    pub day_temp: Option<i32>,
+    pub night_temp: Option<i32>,
+}
+
+/// Named preset of a [profiles.<name>] section, replacing the top-level
+/// settings it gives (all keys optional)
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub day_temp: Option<i32>,
+    pub night_temp: Option<i32>,
+    pub brightness: Option<f64>,
+}
+
+/// Latitude in degrees: -90 to 90, south is negative
+pub fn validate_latitude(latitude: f64) -> Result<f64, String> {
+    if (-90.0..=90.0).contains(&latitude) {
+        Ok(latitude)
+    } else {
+        Err(format!("latitude {} is not between -90 and 90 (south is negative)", latitude))
+    }
+}
+
+/// Longitude in degrees: -180 to 180, west is negative
+pub fn validate_longitude(longitude: f64) -> Result<f64, String> {
+    if (-180.0..=180.0).contains(&longitude) {
+        Ok(longitude)
+    } else {
+        Err(format!("longitude {} is not between -180 and 180 (west is negative)", longitude))
+    }
+}
+
+// Stop times within the day, at most one stop per minute, displayable temperatures
+fn validate_stops(stops: &[(i32, i32)]) -> Result<(), String> {
+    let mut minutes = Vec::with_capacity(stops.len());
+    for &(minute, kelvin) in stops {
+        if !(0..MINUTES_PER_DAY).contains(&minute) {
+            return Err(format!("stops: minute {} is not between 0 and {}", minute, MINUTES_PER_DAY - 1));
+        }
+        if kelvin < TEMPERATURE_ZERO {
+            return Err(format!("stops: {}K is below the displayable minimum of {}K", kelvin, TEMPERATURE_ZERO));
+        }
+        minutes.push(minute);
+    }
+    minutes.sort_unstable();
+    if let Some(pair) = minutes.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(format!("stops: minute {} is given twice", pair[0]));
+    }
+    Ok(())
+}
+
+/// Weekday of a [schedule.<name>] section ("saturday", "sat", any case)
+pub fn parse_weekday(name: &str) -> Result<Weekday, String> {
+    name.parse::<Weekday>()
+        .map_err(|_| format!("[schedule.{}]: unknown day of the week", name))
+}
+
+/// Config file location: $XDG_CONFIG_HOME/colorwarm/config.toml,
+/// falling back to $HOME/.config/colorwarm/config.toml
+pub fn config_path() -> Option<PathBuf> {
+    let base = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(base.join("colorwarm").join("config.toml"))
+}
+
+/// Directory for state kept across reboots: $XDG_STATE_HOME/colorwarm,
+/// falling back to $HOME/.local/state/colorwarm
+pub fn state_dir() -> Option<PathBuf> {
+    let base = match env::var("XDG_STATE_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".local").join("state"),
+    };
+    Some(base.join("colorwarm"))
+}
+
+/// Read and validate the config file. A missing file gives the defaults.
+/// COLORWARM_DAY_TEMP and COLORWARM_NIGHT_TEMP fill in the temperatures the
+/// file leaves unset (defaults < environment < config file < command line).
+pub fn try_load_config() -> Result<Config, String> {
+    let mut config = read_config_file()?;
+    config.day_temp = config.day_temp.or(env_temperature("COLORWARM_DAY_TEMP")?);
+    config.night_temp = config.night_temp.or(env_temperature("COLORWARM_NIGHT_TEMP")?);
+    Ok(config)
+}
+
+// Temperature from the environment, if set
+fn env_temperature(name: &str) -> Result<Option<i32>, String> {
+    match env::var(name) {
+        Ok(value) if !value.trim().is_empty() => value
+            .trim()
+            .parse::<i32>()
+            .map(Some)
+            .map_err(|_| format!("{}: invalid temperature '{}'", name, value)),
+        _ => Ok(None),
+    }
+}
+
+fn read_config_file() -> Result<Config, String> {
+    let path = match config_path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+    };
+
+    let config: Config = toml::from_str(&content)
+        .map_err(|e| format!("invalid config file {}: {}", path.display(), e))?;
+
+    if config.latitude.is_some() != config.longitude.is_some() {
+        return Err(format!("{}: latitude and longitude must be given together", path.display()));
+    }
+    config.latitude.map(validate_latitude).transpose().map_err(|e| format!("{}: {}", path.display(), e))?;
+    config.longitude.map(validate_longitude).transpose().map_err(|e| format!("{}: {}", path.display(), e))?;
+    for (key, minutes) in [
+        ("transition_minutes", config.transition_minutes),
+        ("dawn_duration", config.dawn_duration),
+        ("dusk_duration", config.dusk_duration),
+    ] {
+        if minutes.is_some_and(|minutes| !(0..=720).contains(&minutes)) {
+            return Err(format!("{}: {} must be between 0 and 720", path.display(), key));
+        }
+    }
+    for (key, time) in [
+        ("min_transition_time", &config.min_transition_time),
+        ("max_transition_time", &config.max_transition_time),
+    ] {
+        if let Some(time) = time {
+            parse_clock_time(time).map_err(|e| format!("{}: {}: {}", path.display(), key, e))?;
+        }
+    }
+    if let Some(minutes) = config.offset_minutes {
+        if !(-180..=180).contains(&minutes) {
+            return Err(format!("{}: offset_minutes must be between -180 and 180", path.display()));
+        }
+    }
+
+    validate_stops(&config.stops).map_err(|e| format!("{}: {}", path.display(), e))?;
+    for (name, day) in &config.schedule {
+        parse_weekday(name).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some(minutes) = day.offset {
+            if !(-180..=180).contains(&minutes) {
+                return Err(format!("{}: [schedule.{}]: offset must be between -180 and 180", path.display(), name));
+            }
+        }
+    }
+
+    validate_brightness(config.brightness).map_err(|e| format!("{}: {}", path.display(), e))?;
+    for (name, profile) in &config.profiles {
+        validate_brightness(profile.brightness).map_err(|e| format!("{}: [profiles.{}]: {}", path.display(), name, e))?;
+    }
+
+    Ok(config)
+}
+
+fn validate_brightness(brightness: Option<f64>) -> Result<(), String> {
+    match brightness {
+        Some(b) if !(0.1..=1.0).contains(&b) => Err("brightness must be between 0.1 and 1.0".to_string()),
+        _ => Ok(()),
+    }
+}
+
+impl Config {
+    /// Fill the settings this config leaves unset from another one
+    pub fn or(self, other: Config) -> Config {
+        Config {
+            day_temp: self.day_temp.or(other.day_temp),
+            night_temp: self.night_temp.or(other.night_temp),
+            latitude: self.latitude.or(other.latitude),
+            longitude: self.longitude.or(other.longitude),
+            transition_minutes: self.transition_minutes.or(other.transition_minutes),
+            dawn_duration: self.dawn_duration.or(other.dawn_duration),
+            dusk_duration: self.dusk_duration.or(other.dusk_duration),
+            min_transition_time: self.min_transition_time.or(other.min_transition_time),
+            max_transition_time: self.max_transition_time.or(other.max_transition_time),
+            offset_minutes: self.offset_minutes.or(other.offset_minutes),
+            curve: self.curve.or(other.curve),
+            twilight: self.twilight.or(other.twilight),
+            brightness: self.brightness.or(other.brightness),
+            verbose: self.verbose.or(other.verbose),
+            outputs: if self.outputs.is_empty() { other.outputs } else { self.outputs },
+            stops: if self.stops.is_empty() { other.stops } else { self.stops },
+            schedule: if self.schedule.is_empty() { other.schedule } else { self.schedule },
+            profile: self.profile.or(other.profile),
+            profiles: if self.profiles.is_empty() { other.profiles } else { self.profiles },
+        }
+    }
+
+    /// Replace the top-level settings with those of the selected profile
+    pub fn apply_profile(mut self) -> Result<Config, String> {
+        let Some(name) = &self.profile else {
+            return Ok(self);
+        };
+        let Some(profile) = self.profiles.get(name).copied() else {
+            let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            return Err(if names.is_empty() {
+                format!("unknown profile '{}' (the config file has no [profiles.<name>] section)", name)
+            } else {
+                format!("unknown profile '{}' (defined: {})", name, names.join(", "))
+            });
+        };
+        self.day_temp = profile.day_temp.or(self.day_temp);
+        self.night_temp = profile.night_temp.or(self.night_temp);
+        self.brightness = profile.brightness.or(self.brightness);
+        Ok(self)
+    }
+}
+
+/// Default redshift config: $XDG_CONFIG_HOME/redshift.conf or ~/.config/redshift.conf
+pub fn redshift_config_path() -> Option<PathBuf> {
+    let base = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(base.join("redshift.conf"))
+}
+
+/// Read the settings colorwarm understands from a redshift.conf INI file:
+/// temp-day, temp-night and brightness (or brightness-day) from [redshift],
+/// lat and lon from [manual]. Other keys are ignored.
+pub fn import_redshift(path: &Path) -> Result<Config, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+
+    let mut config = Config::default();
+    let mut brightness_day = None;
+    let mut section = String::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let invalid = || format!("{}:{}: invalid value '{}' for {}", path.display(), number + 1, value, key);
+
+        match (section.as_str(), key) {
+            ("redshift", "temp-day") => config.day_temp = Some(value.parse().map_err(|_| invalid())?),
+            ("redshift", "temp-night") => config.night_temp = Some(value.parse().map_err(|_| invalid())?),
+            ("redshift", "brightness") => config.brightness = Some(value.parse().map_err(|_| invalid())?),
+            ("redshift", "brightness-day") => brightness_day = Some(value.parse().map_err(|_| invalid())?),
+            ("manual", "lat") => config.latitude = Some(value.parse().map_err(|_| invalid())?),
+            ("manual", "lon") => config.longitude = Some(value.parse().map_err(|_| invalid())?),
+            _ => {}
+        }
+    }
+    config.brightness = config.brightness.or(brightness_day);
+
+    if config.latitude.is_some() != config.longitude.is_some() {
+        return Err(format!("{}: lat and lon must be given together", path.display()));
+    }
+    config.latitude.map(validate_latitude).transpose().map_err(|e| format!("{}: {}", path.display(), e))?;
+    config.longitude.map(validate_longitude).transpose().map_err(|e| format!("{}: {}", path.display(), e))?;
+    validate_brightness(config.brightness).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    Ok(config)
+}
+
+/// Per-user runtime directory: $XDG_RUNTIME_DIR, else /run/user/<uid>
+pub fn runtime_dir() -> PathBuf {
+    match env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })),
+    }
+}
@@ -0,0 +1,244 @@
+// Control socket for the daemon: one text command per connection, one reply
+//
+// Commands: status, toggle, set <kelvin>, auto, reload, profile <name>,
+// pause [seconds], resume
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Timelike;
+use clap::ArgMatches;
+use log::warn;
+
+use crate::app::{reload_state, set_neutral, switch_profile, todays_schedule, AppState};
+use crate::backend::Backend;
+use crate::config::runtime_dir;
+use crate::schedule::{format_time, target_kelvin};
+use crate::sun::MonthlyTimes;
+use crate::xsct::{TempStatus, TEMPERATURE_ZERO};
+
+const SOCKET_NAME: &str = "colorwarm.sock";
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub fn socket_path() -> PathBuf {
+    runtime_dir().join(SOCKET_NAME)
+}
+
+pub(crate) struct ControlSocket {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    pub fn bind() -> io::Result<Self> {
+        let path = socket_path();
+
+        // A daemon already answering on the socket must not be hijacked
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is in use by another instance", path.display()),
+            ));
+        }
+        let _ = std::fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        Ok(ControlSocket { listener, path })
+    }
+
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.listener.as_raw_fd()
+    }
+
+    // Serve pending connections without blocking the main loop
+    pub fn poll(
+        &self,
+        args: &ArgMatches,
+        state: &mut AppState,
+        monthly_times: &mut MonthlyTimes,
+        backend: &dyn Backend,
+    ) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Err(e) = serve_client(stream, args, state, monthly_times, backend) {
+                warn!("Control socket error: {}", e);
+            }
+        }
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn serve_client(
+    stream: UnixStream,
+    args: &ArgMatches,
+    state: &mut AppState,
+    monthly_times: &mut MonthlyTimes,
+    backend: &dyn Backend,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let reply = handle_command(line.trim(), args, state, monthly_times, backend);
+    (&stream).write_all(reply.as_bytes())?;
+    (&stream).write_all(b"\n")
+}
+
+fn handle_command(
+    command: &str,
+    args: &ArgMatches,
+    state: &mut AppState,
+    monthly_times: &mut MonthlyTimes,
+    backend: &dyn Backend,
+) -> String {
+    let mut words = command.split_whitespace();
+    let (verb, argument) = (words.next(), words.next());
+    // No command takes more than one argument
+    if words.next().is_some() {
+        return format!("error: unknown command '{}'", command);
+    }
+    match (verb, argument) {
+        (Some("status"), None) => status(state, monthly_times, backend),
+        (Some("toggle"), None) => {
+            let current = state.forced().unwrap_or_else(|| scheduled_kelvin(state, monthly_times));
+            let midpoint = (state.curve.day_temp + state.curve.night_temp) / 2;
+            let forced = if current > midpoint {
+                state.curve.night_temp
+            } else {
+                state.curve.day_temp
+            };
+            force(state, backend, forced)
+        }
+        (Some("set"), Some(value)) => match value.parse::<i32>() {
+            Ok(kelvin) if kelvin >= TEMPERATURE_ZERO => force(state, backend, kelvin),
+            _ => format!("error: invalid temperature '{}'", value),
+        },
+        (Some("auto"), None) => {
+            state.forced_temp = None;
+            state.resume();
+            let kelvin = state.forced().unwrap_or_else(|| scheduled_kelvin(state, monthly_times));
+            apply(state, backend, kelvin)
+        }
+        (Some("pause"), None) => pause(state, backend, None),
+        (Some("pause"), Some(value)) => match value.parse::<u64>() {
+            Ok(seconds) if seconds > 0 => pause(state, backend, Some(Duration::from_secs(seconds))),
+            _ => format!("error: invalid pause duration '{}'", value),
+        },
+        (Some("resume"), None) => {
+            state.resume();
+            let kelvin = state.forced().unwrap_or_else(|| scheduled_kelvin(state, monthly_times));
+            apply(state, backend, kelvin)
+        }
+        (Some("reload"), None) => {
+            reload_state(args, state, monthly_times);
+            backend.set_output_offsets(&state.output_offsets);
+            apply_unless_paused(state, monthly_times, backend)
+        }
+        (Some("profile"), Some(name)) => match switch_profile(args, state, monthly_times, name) {
+            Ok(()) => {
+                backend.set_output_offsets(&state.output_offsets);
+                apply_unless_paused(state, monthly_times, backend)
+            }
+            Err(e) => format!("error: {}", e),
+        },
+        _ => format!("error: unknown command '{}'", command),
+    }
+}
+
+// Hold a temperature until `auto` or `reload`
+fn force(state: &mut AppState, backend: &dyn Backend, kelvin: i32) -> String {
+    state.forced_temp = Some(kelvin);
+    state.resume();
+    apply(state, backend, kelvin)
+}
+
+// Neutral gamma and no adjustments until `resume` (or the end of the pause)
+fn pause(state: &mut AppState, backend: &dyn Backend, duration: Option<Duration>) -> String {
+    state.paused = true;
+    state.pause_until = duration.map(|duration| state.clock.system_time() + duration);
+    match set_neutral(state, backend) {
+        Ok(_) => match duration {
+            Some(duration) => format!("ok paused for {}s", duration.as_secs()),
+            None => "ok paused".to_string(),
+        },
+        Err(e) => format!("error: {}", e),
+    }
+}
+
+// Within --min-temp/--max-temp, the reply telling the temperature applied
+fn apply(state: &AppState, backend: &dyn Backend, kelvin: i32) -> String {
+    let temp = state.limited(TempStatus { temp: kelvin, brightness: state.brightness });
+    match backend.set_temperature(temp) {
+        Ok(()) => format!("ok {}K", temp.temp),
+        Err(e) => format!("error: {}", e),
+    }
+}
+
+// New settings take effect now, or when a pause they leave running ends
+fn apply_unless_paused(state: &AppState, monthly_times: &MonthlyTimes, backend: &dyn Backend) -> String {
+    if state.paused {
+        return "ok paused".to_string();
+    }
+    let kelvin = state.forced().unwrap_or_else(|| scheduled_kelvin(state, monthly_times));
+    apply(state, backend, kelvin)
+}
+
+// Temperature the schedule asks for right now
+fn scheduled_kelvin(state: &AppState, monthly_times: &MonthlyTimes) -> i32 {
+    let (sunrise, sunset, curve) = todays_schedule(state, monthly_times);
+    let now = state.now();
+    target_kelvin((now.hour() * 60 + now.minute()) as f64, sunrise, sunset, &curve)
+}
+
+fn status(state: &AppState, monthly_times: &MonthlyTimes, backend: &dyn Backend) -> String {
+    let (sunrise, sunset, _) = todays_schedule(state, monthly_times);
+    let (temp, brightness) = match backend.get_temperature() {
+        Ok(current) => (format!("{}K", current.temp), format!("{:.2}", current.brightness)),
+        Err(_) => ("unknown".to_string(), "unknown".to_string()),
+    };
+    let paused = match state.pause_until {
+        _ if !state.paused => "no".to_string(),
+        Some(until) => {
+            let left = until.duration_since(state.clock.system_time()).unwrap_or_default();
+            format!("yes ({}s left)", left.as_secs())
+        }
+        None => "yes".to_string(),
+    };
+    format!(
+        "temperature: {}\nbrightness: {}\ntarget: {}K\nmode: {}\nprofile: {}\npaused: {}\nsunrise: {}\nsunset: {}\nlocation: {}",
+        temp,
+        brightness,
+        state.forced().unwrap_or_else(|| scheduled_kelvin(state, monthly_times)),
+        if state.forced().is_some() { "forced" } else { "auto" },
+        state.profile.as_deref().unwrap_or("none"),
+        paused,
+        format_time(sunrise),
+        format_time(sunset),
+        state.location_name
+    )
+}
+
+// Client side: `colorwarm ctl <command>`
+pub fn send_command(command: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let path = socket_path();
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|e| format!("cannot connect to {} (is the daemon running?): {}", path.display(), e))?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    stream.write_all(command.as_bytes())?;
+    stream.write_all(b"\n")?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply.trim_end().to_string())
+}
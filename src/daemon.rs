@@ -0,0 +1,111 @@
+// Daemon management from the command line: `colorwarm stop` and `colorwarm status`
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::config::runtime_dir;
+use crate::control;
+
+const STOP_TIMEOUT_MS: u64 = 3000; // How long `stop` waits for the daemon to exit
+const STOP_POLL_MS: u64 = 100;
+
+/// Default pid file of the daemon, in the per-user runtime directory
+pub fn default_pid_file() -> PathBuf {
+    runtime_dir().join("colorwarm.pid")
+}
+
+/// Daemon standard output log
+pub fn log_file() -> PathBuf {
+    runtime_dir().join("colorwarm.log")
+}
+
+/// Daemon standard error log
+pub fn err_file() -> PathBuf {
+    runtime_dir().join("colorwarm.err")
+}
+
+/// Create (or truncate) a file only readable by the current user
+pub fn create_private_file(path: &Path) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // The mode is only applied on creation
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+/// Pid of the running daemon, or `None` if it is not running.
+/// A pid file left behind by a dead process is removed.
+pub fn running_pid(pid_file: &Path) -> Result<Option<i32>, Box<dyn std::error::Error>> {
+    let content = match fs::read_to_string(pid_file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("cannot read {}: {}", pid_file.display(), e).into()),
+    };
+
+    let pid = match content.trim().parse::<i32>() {
+        Ok(pid) if pid > 0 => pid,
+        _ => {
+            eprintln!("Removing invalid pid file {}", pid_file.display());
+            fs::remove_file(pid_file)?;
+            return Ok(None);
+        }
+    };
+
+    if process_alive(pid) {
+        Ok(Some(pid))
+    } else {
+        eprintln!("Removing stale pid file {} (process {} is gone)", pid_file.display(), pid);
+        fs::remove_file(pid_file)?;
+        Ok(None)
+    }
+}
+
+// Signal 0 only checks that the process exists
+fn process_alive(pid: i32) -> bool {
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Send SIGTERM to the daemon and wait for it to exit.
+/// Returns false if no daemon was running.
+pub fn stop(pid_file: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    let pid = match running_pid(pid_file)? {
+        Some(pid) => pid,
+        None => return Ok(false),
+    };
+
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        return Err(format!("cannot stop process {}: {}", pid, io::Error::last_os_error()).into());
+    }
+
+    for _ in 0..STOP_TIMEOUT_MS / STOP_POLL_MS {
+        if !process_alive(pid) {
+            return Ok(true);
+        }
+        sleep(Duration::from_millis(STOP_POLL_MS));
+    }
+    Err(format!("process {} did not exit after SIGTERM", pid).into())
+}
+
+/// Human readable daemon state, with the last temperature when the control
+/// socket answers. Returns None if no daemon is running.
+pub fn status(pid_file: &Path) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let pid = match running_pid(pid_file)? {
+        Some(pid) => pid,
+        None => return Ok(None),
+    };
+
+    let details = match control::send_command("status") {
+        Ok(reply) => reply,
+        Err(e) => format!("control socket unavailable: {}", e),
+    };
+    Ok(Some(format!("colorwarm is running (pid {})\n{}", pid, details)))
+}
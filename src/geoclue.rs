@@ -0,0 +1,60 @@
+// Location from the GeoClue2 D-Bus service (--geoclue)
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use zbus::blocking::proxy::Builder;
+use zbus::blocking::{Connection, Proxy};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedObjectPath;
+
+const SERVICE: &str = "org.freedesktop.GeoClue2";
+const MANAGER_PATH: &str = "/org/freedesktop/GeoClue2/Manager";
+const DESKTOP_ID: &str = "colorwarm";
+const ACCURACY_CITY: u32 = 4; // GClueAccuracyLevel: enough for sun times
+const FIX_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Proxy reading properties straight from the bus: nothing runs the
+// signal handlers that would keep a cache up to date
+fn proxy<'a>(conn: &Connection, path: &'a str, interface: &'a str) -> zbus::Result<Proxy<'a>> {
+    Builder::new(conn)
+        .destination(SERVICE)?
+        .path(path)?
+        .interface(interface)?
+        .cache_properties(CacheProperties::No)
+        .build()
+}
+
+/// Ask GeoClue2 for the current (latitude, longitude). Fails if the service
+/// is missing, the request is denied or no fix arrives within a few seconds.
+pub fn locate() -> Result<(f64, f64), Box<dyn std::error::Error>> {
+    let conn = Connection::system()?;
+    let manager = proxy(&conn, MANAGER_PATH, "org.freedesktop.GeoClue2.Manager")?;
+    let client_path: OwnedObjectPath = manager.call("GetClient", &())?;
+
+    let client = proxy(&conn, client_path.as_str(), "org.freedesktop.GeoClue2.Client")?;
+    client.set_property("DesktopId", DESKTOP_ID)?;
+    client.set_property("RequestedAccuracyLevel", ACCURACY_CITY)?;
+    client.call_method("Start", &())?;
+
+    let location = wait_for_location(&conn, &client);
+    let _ = client.call_method("Stop", &());
+    location
+}
+
+// The client's Location property stays "/" until the first fix
+fn wait_for_location(conn: &Connection, client: &Proxy) -> Result<(f64, f64), Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    loop {
+        let path: OwnedObjectPath = client.get_property("Location")?;
+        if path.as_str() != "/" {
+            let location = proxy(conn, path.as_str(), "org.freedesktop.GeoClue2.Location")?;
+            return Ok((location.get_property("Latitude")?, location.get_property("Longitude")?));
+        }
+        if start.elapsed() >= FIX_TIMEOUT {
+            return Err("no location fix received".into());
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,52 @@
+// Location from a running gpsd (--gpsd), for machines that travel
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+pub const DEFAULT_ADDRESS: &str = "localhost:2947";
+const WATCH: &[u8] = b"?WATCH={\"enable\":true,\"json\":true};\n";
+const FIX_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_FIX_AGE: Duration = Duration::from_secs(60); // Older reports are stale
+
+/// Ask gpsd at `address` (host:port) for the current (latitude, longitude).
+/// Fails if gpsd is down, or no fresh 2D/3D fix arrives within a few seconds.
+pub fn locate(address: &str) -> Result<(f64, f64), Box<dyn std::error::Error>> {
+    let socket = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("cannot resolve {}", address))?;
+    let mut stream = TcpStream::connect_timeout(&socket, FIX_TIMEOUT)?;
+    stream.set_read_timeout(Some(FIX_TIMEOUT))?;
+    stream.write_all(WATCH)?;
+
+    // gpsd sends VERSION, DEVICES and WATCH first, then one TPV
+    // (time-position-velocity) report per receiver cycle
+    let start = Instant::now();
+    for line in BufReader::new(stream).lines() {
+        if let Some(position) = fix(&serde_json::from_str(&line?)?) {
+            return Ok(position);
+        }
+        if start.elapsed() >= FIX_TIMEOUT {
+            break;
+        }
+    }
+    Err("no position fix received".into())
+}
+
+// Position of a TPV report with a recent 2D (mode 2) or 3D (mode 3) fix
+fn fix(report: &Value) -> Option<(f64, f64)> {
+    if report["class"] != "TPV" || report["mode"].as_u64()? < 2 {
+        return None;
+    }
+    if let Some(time) = report["time"].as_str() {
+        let time = DateTime::parse_from_rfc3339(time).ok()?.with_timezone(&Utc);
+        if (Utc::now() - time).to_std().is_ok_and(|age| age > MAX_FIX_AGE) {
+            return None;
+        }
+    }
+    Some((report["lat"].as_f64()?, report["lon"].as_f64()?))
+}
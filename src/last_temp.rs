@@ -0,0 +1,42 @@
+// Last applied temperature, kept across daemon restarts so that startup can
+// fade from it instead of flashing through whatever gamma the session left
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::config::state_dir;
+use crate::xsct::TempStatus;
+
+const FILE_NAME: &str = "last_temperature";
+const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60); // Older files are ignored
+
+fn path() -> Option<PathBuf> {
+    Some(state_dir()?.join(FILE_NAME))
+}
+
+/// Record the temperature just applied ("<kelvin> <brightness>")
+pub fn save(temp: TempStatus) -> io::Result<()> {
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, format!("{} {:.3}\n", temp.temp, temp.brightness))
+}
+
+/// Temperature saved by a previous run, unless missing, unreadable or more
+/// than a day old
+pub fn load() -> Option<TempStatus> {
+    let path = path()?;
+    let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+    if !SystemTime::now().duration_since(modified).is_ok_and(|age| age <= MAX_AGE) {
+        return None;
+    }
+
+    let content = fs::read_to_string(&path).ok()?;
+    let mut fields = content.split_whitespace();
+    let temp = fields.next()?.parse::<i32>().ok()?;
+    let brightness = fields.next()?.parse::<f64>().ok().filter(|b| b.is_finite())?;
+    Some(TempStatus { temp, brightness })
+}
@@ -0,0 +1,42 @@
+//! ColorWarm: automatic screen color temperature following the sun.
+//!
+//! The library exposes the solar computation, the temperature curve, the
+//! timezone helpers and the integrated xsct gamma functions. The X11
+//! connection is always passed in by the caller.
+
+pub mod backend;
+pub mod backlight;
+pub mod config;
+pub mod control;
+pub mod daemon;
+pub mod geoclue;
+pub mod gpsd;
+pub mod last_temp;
+pub mod location;
+pub mod location_cache;
+pub mod logging;
+pub mod metrics;
+pub mod notify;
+pub mod override_file;
+pub mod schedule;
+pub mod service;
+pub mod sun;
+pub mod suntimes;
+pub mod systemd;
+#[cfg(feature = "wayland")]
+pub mod wayland;
+#[cfg(feature = "x11")]
+pub mod x11;
+pub mod xsct;
+
+mod app;
+
+pub use app::{check_service_options, run};
+pub use location::{find_city, get_longitude_offset, guess_location_from_system, timezone_to_location_name};
+pub use schedule::{
+    ease, kelvin_to_mired, mired_to_kelvin, target_kelvin, Clock, CurveSettings, CurveShape, FixedClock, SystemClock,
+};
+pub use sun::{compute_sun_times, compute_sun_times_in, get_smoothed_day_times, MonthlyTimes};
+#[cfg(feature = "x11")]
+pub use xsct::{get_sct_for_screen, sct_for_screen};
+pub use xsct::TempStatus;
@@ -0,0 +1,872 @@
+// Location detection from the system timezone
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+pub const DEFAULT_TIMEZONE: &str = "Europe/Brussels";
+pub const DEFAULT_COORDINATES: (f64, f64) = (50.85, 4.35); // Brussels
+
+// Approximate (latitude, longitude) of the cities named by
+// timezone_to_location_name, for --city
+const CITY_COORDINATES: &[(&str, f64, f64)] = &[
+    ("Europe/Paris", 48.86, 2.35),
+    ("Europe/Brussels", 50.85, 4.35),
+    ("Europe/London", 51.51, -0.13),
+    ("Europe/Berlin", 52.52, 13.40),
+    ("Europe/Madrid", 40.42, -3.70),
+    ("Europe/Rome", 41.90, 12.50),
+    ("Europe/Amsterdam", 52.37, 4.90),
+    ("Europe/Lisbon", 38.72, -9.14),
+    ("Europe/Vienna", 48.21, 16.37),
+    ("Europe/Zurich", 47.38, 8.54),
+    ("Europe/Warsaw", 52.23, 21.01),
+    ("Europe/Prague", 50.08, 14.44),
+    ("Europe/Stockholm", 59.33, 18.07),
+    ("Europe/Oslo", 59.91, 10.75),
+    ("Europe/Copenhagen", 55.68, 12.57),
+    ("Europe/Helsinki", 60.17, 24.94),
+    ("Europe/Moscow", 55.76, 37.62),
+    ("Europe/Kiev", 50.45, 30.52),
+    ("Europe/Bucharest", 44.43, 26.10),
+    ("Europe/Budapest", 47.50, 19.04),
+    ("Europe/Athens", 37.98, 23.73),
+    ("Europe/Dublin", 53.35, -6.26),
+    ("Europe/Sofia", 42.70, 23.32),
+    ("Europe/Belgrade", 44.79, 20.45),
+    ("Europe/Zagreb", 45.81, 15.98),
+    ("Europe/Sarajevo", 43.86, 18.41),
+    ("Europe/Skopje", 42.00, 21.43),
+    ("Europe/Tirane", 41.33, 19.82),
+    ("Europe/Minsk", 53.90, 27.56),
+    ("Europe/Riga", 56.95, 24.11),
+    ("Europe/Vilnius", 54.69, 25.28),
+    ("Europe/Tallinn", 59.44, 24.75),
+    ("Europe/Chisinau", 47.01, 28.86),
+    ("Europe/Bratislava", 48.15, 17.11),
+    ("Europe/Ljubljana", 46.06, 14.51),
+    ("Europe/Luxembourg", 49.61, 6.13),
+    ("Europe/Valletta", 35.90, 14.51),
+    ("Europe/Monaco", 43.73, 7.42),
+    ("Europe/San_Marino", 43.94, 12.45),
+    ("Europe/Vatican", 41.90, 12.45),
+    ("Europe/Andorra", 42.51, 1.52),
+    ("Europe/Istanbul", 41.01, 28.98),
+    ("Europe/Nicosia", 35.17, 33.36),
+    ("America/New_York", 40.71, -74.01),
+    ("America/Chicago", 41.88, -87.63),
+    ("America/Denver", 39.74, -104.99),
+    ("America/Los_Angeles", 34.05, -118.24),
+    ("America/Phoenix", 33.45, -112.07),
+    ("America/Anchorage", 61.22, -149.90),
+    ("America/Honolulu", 21.31, -157.86),
+    ("America/Toronto", 43.65, -79.38),
+    ("America/Vancouver", 49.28, -123.12),
+    ("America/Montreal", 45.50, -73.57),
+    ("America/Winnipeg", 49.90, -97.14),
+    ("America/Edmonton", 53.55, -113.49),
+    ("America/Mexico_City", 19.43, -99.13),
+    ("America/Cancun", 21.16, -86.85),
+    ("America/Havana", 23.11, -82.37),
+    ("America/Port-au-Prince", 18.59, -72.31),
+    ("America/Santo_Domingo", 18.49, -69.93),
+    ("America/San_Juan", 18.47, -66.11),
+    ("America/Nassau", 25.05, -77.35),
+    ("America/Jamaica", 17.97, -76.79),
+    ("America/Managua", 12.11, -86.24),
+    ("America/Panama", 8.98, -79.52),
+    ("America/Bogota", 4.71, -74.07),
+    ("America/Lima", -12.05, -77.04),
+    ("America/Caracas", 10.48, -66.90),
+    ("America/Georgetown", 6.80, -58.16),
+    ("America/Paramaribo", 5.85, -55.20),
+    ("America/Santiago", -33.45, -70.67),
+    ("America/Buenos_Aires", -34.60, -58.38),
+    ("America/Sao_Paulo", -23.55, -46.63),
+    ("America/Rio_de_Janeiro", -22.91, -43.17),
+    ("America/Fortaleza", -3.73, -38.52),
+    ("America/Asuncion", -25.26, -57.58),
+    ("America/Montevideo", -34.90, -56.16),
+    ("America/La_Paz", -16.49, -68.12),
+    ("America/Guayaquil", -2.19, -79.89),
+    ("America/Quito", -0.18, -78.47),
+    ("America/Cayenne", 4.92, -52.31),
+    ("Asia/Tokyo", 35.68, 139.69),
+    ("Asia/Shanghai", 31.23, 121.47),
+    ("Asia/Beijing", 39.90, 116.41),
+    ("Asia/Hong_Kong", 22.32, 114.17),
+    ("Asia/Macau", 22.20, 113.54),
+    ("Asia/Taipei", 25.03, 121.57),
+    ("Asia/Seoul", 37.57, 126.98),
+    ("Asia/Pyongyang", 39.04, 125.76),
+    ("Asia/Ulaanbaatar", 47.89, 106.91),
+    ("Asia/Singapore", 1.35, 103.82),
+    ("Asia/Kuala_Lumpur", 3.15, 101.69),
+    ("Asia/Jakarta", -6.21, 106.85),
+    ("Asia/Bangkok", 13.76, 100.50),
+    ("Asia/Manila", 14.60, 120.98),
+    ("Asia/Ho_Chi_Minh", 10.82, 106.63),
+    ("Asia/Hanoi", 21.03, 105.85),
+    ("Asia/Phnom_Penh", 11.56, 104.92),
+    ("Asia/Vientiane", 17.98, 102.63),
+    ("Asia/Yangon", 16.87, 96.20),
+    ("Asia/Dhaka", 23.81, 90.41),
+    ("Asia/Kolkata", 22.57, 88.36),
+    ("Asia/Delhi", 28.61, 77.21),
+    ("Asia/Mumbai", 19.08, 72.88),
+    ("Asia/Chennai", 13.08, 80.27),
+    ("Asia/Karachi", 24.86, 67.01),
+    ("Asia/Lahore", 31.55, 74.34),
+    ("Asia/Kabul", 34.56, 69.21),
+    ("Asia/Tehran", 35.69, 51.39),
+    ("Asia/Baghdad", 33.31, 44.36),
+    ("Asia/Riyadh", 24.71, 46.68),
+    ("Asia/Dubai", 25.20, 55.27),
+    ("Asia/Muscat", 23.59, 58.41),
+    ("Asia/Doha", 25.29, 51.53),
+    ("Asia/Kuwait", 29.38, 47.99),
+    ("Asia/Bahrain", 26.23, 50.59),
+    ("Asia/Amman", 31.95, 35.93),
+    ("Asia/Beirut", 33.89, 35.50),
+    ("Asia/Damascus", 33.51, 36.28),
+    ("Asia/Jerusalem", 31.77, 35.21),
+    ("Asia/Yerevan", 40.18, 44.51),
+    ("Asia/Baku", 40.41, 49.87),
+    ("Asia/Tbilisi", 41.72, 44.79),
+    ("Asia/Ashgabat", 37.96, 58.33),
+    ("Asia/Tashkent", 41.30, 69.24),
+    ("Asia/Dushanbe", 38.56, 68.77),
+    ("Asia/Bishkek", 42.87, 74.59),
+    ("Asia/Almaty", 43.24, 76.89),
+    ("Asia/Colombo", 6.93, 79.86),
+    ("Asia/Kathmandu", 27.72, 85.32),
+    ("Asia/Thimphu", 27.47, 89.64),
+    ("Asia/Male", 4.18, 73.51),
+    ("Africa/Cairo", 30.04, 31.24),
+    ("Africa/Johannesburg", -26.20, 28.05),
+    ("Africa/Cape_Town", -33.92, 18.42),
+    ("Africa/Lagos", 6.52, 3.38),
+    ("Africa/Kinshasa", -4.44, 15.27),
+    ("Africa/Nairobi", -1.29, 36.82),
+    ("Africa/Addis_Ababa", 9.03, 38.74),
+    ("Africa/Dar_es_Salaam", -6.79, 39.21),
+    ("Africa/Khartoum", 15.50, 32.56),
+    ("Africa/Algiers", 36.75, 3.06),
+    ("Africa/Casablanca", 33.57, -7.59),
+    ("Africa/Tunis", 36.81, 10.18),
+    ("Africa/Tripoli", 32.89, 13.19),
+    ("Africa/Accra", 5.60, -0.19),
+    ("Africa/Dakar", 14.72, -17.47),
+    ("Africa/Abidjan", 5.36, -4.01),
+    ("Africa/Bamako", 12.64, -8.00),
+    ("Africa/Luanda", -8.84, 13.23),
+    ("Africa/Lusaka", -15.39, 28.32),
+    ("Africa/Harare", -17.83, 31.05),
+    ("Africa/Maputo", -25.97, 32.57),
+    ("Africa/Kampala", 0.35, 32.58),
+    ("Africa/Kigali", -1.94, 30.06),
+    ("Africa/Windhoek", -22.56, 17.08),
+    ("Australia/Sydney", -33.87, 151.21),
+    ("Australia/Melbourne", -37.81, 144.96),
+    ("Australia/Brisbane", -27.47, 153.03),
+    ("Australia/Perth", -31.95, 115.86),
+    ("Australia/Adelaide", -34.93, 138.60),
+    ("Australia/Hobart", -42.88, 147.33),
+    ("Australia/Darwin", -12.46, 130.84),
+    ("Australia/Canberra", -35.28, 149.13),
+    ("Pacific/Auckland", -36.85, 174.76),
+    ("Pacific/Wellington", -41.29, 174.78),
+    ("Pacific/Fiji", -18.14, 178.44),
+    ("Pacific/Port_Moresby", -9.44, 147.18),
+    ("Pacific/Noumea", -22.28, 166.46),
+    ("Pacific/Guam", 13.44, 144.79),
+    ("Pacific/Tahiti", -17.54, -149.57),
+    ("Pacific/Apia", -13.83, -171.76),
+    ("Pacific/Tongatapu", -21.14, -175.20),
+];
+
+// Approximate longitude offset (minutes) of each timezone, grouped by value
+const LONGITUDE_OFFSETS: &[(&[&str], i32)] = &[
+    // Europe (UTC-1 to UTC+3)
+    (&["Atlantic/Azores"], -60),   // Portugal (Azores)
+    (&["Atlantic/Madeira"], -30),  // Portugal (Madeira)
+    (&["Europe/Lisbon", "Atlantic/Canary"], -30), // Portugal, Canary Islands
+    (&["Europe/London", "Europe/Dublin", "Europe/Guernsey", "Europe/Isle_of_Man", "Europe/Jersey"], -30),
+    (&["Africa/Casablanca", "Africa/El_Aaiun"], -30), // Morocco, Western Sahara
+    
+    // Western Europe (UTC+0/+1 depending on DST)
+    (&["Europe/Paris", "Europe/Brussels", "Europe/Amsterdam", "Europe/Luxembourg"], 0),
+    (&["Europe/Monaco", "Europe/Andorra", "Europe/Madrid"], 0),
+    (&["Europe/Gibraltar", "Africa/Algiers", "Africa/Tunis"], 0),
+    
+    // Central Europe (UTC+1/+2)
+    (&["Europe/Berlin", "Europe/Vienna", "Europe/Zurich", "Europe/Rome"], 15),
+    (&["Europe/Vatican", "Europe/San_Marino", "Europe/Malta"], 15),
+    (&["Europe/Prague", "Europe/Warsaw", "Europe/Budapest", "Europe/Bratislava"], 15),
+    (&["Europe/Belgrade", "Europe/Sarajevo", "Europe/Skopje", "Europe/Zagreb"], 15),
+    (&["Europe/Tirane", "Europe/Sofia", "Europe/Bucharest"], 15),
+    (&["Europe/Stockholm", "Europe/Oslo", "Europe/Copenhagen", "Europe/Ljubljana", "Europe/Valletta"], 15),
+    (&["Africa/Cairo"], 15), // Egypt
+    
+    // Eastern Europe (UTC+2/+3)
+    (&["Europe/Helsinki", "Europe/Tallinn", "Europe/Riga", "Europe/Vilnius"], 30),
+    (&["Europe/Kiev", "Europe/Chisinau", "Europe/Uzhgorod", "Europe/Zaporozhye"], 30),
+    (&["Europe/Istanbul", "Europe/Athens", "Europe/Nicosia"], 30),
+    (&["Asia/Beirut", "Asia/Damascus", "Asia/Amman", "Asia/Jerusalem"], 30),
+    (&["Asia/Gaza", "Asia/Hebron"], 30),
+    (&["Africa/Johannesburg", "Africa/Windhoek"], 30), // South Africa, Namibia
+    
+    // Further east Europe/Russia (UTC+3)
+    (&["Europe/Moscow", "Europe/Simferopol", "Europe/Kirov", "Europe/Volgograd"], 45),
+    (&["Europe/Astrakhan", "Europe/Saratov", "Europe/Ulyanovsk"], 45),
+    (&["Europe/Minsk"], 45), // Belarus
+    (&["Europe/Samara"], 60),
+    (&["Asia/Yerevan", "Asia/Tbilisi", "Asia/Baku"], 45),
+    
+    // Middle East (UTC+3 to UTC+4:30)
+    (&["Asia/Riyadh", "Asia/Qatar", "Asia/Bahrain", "Asia/Kuwait"], 45),
+    (&["Asia/Aden", "Asia/Muscat"], 45),
+    (&["Asia/Baghdad", "Asia/Doha"], 45),
+    (&["Asia/Dubai"], 60),
+    (&["Asia/Tehran"], 75), // UTC+3:30
+    (&["Asia/Kabul"], 105), // UTC+4:30
+    
+    // South Asia (UTC+5 to UTC+5:30)
+    (&["Asia/Karachi", "Asia/Tashkent"], 120),
+    (&["Asia/Lahore", "Asia/Ashgabat", "Asia/Dushanbe", "Asia/Male"], 120),
+    (&["Asia/Yekaterinburg"], 120),
+    (&["Asia/Colombo"], 135), // UTC+5:30
+    (&["Asia/Kolkata", "Asia/Calcutta"], 135), // UTC+5:30
+    (&["Asia/Delhi", "Asia/Mumbai", "Asia/Chennai"], 135), // UTC+5:30
+    (&["Asia/Kathmandu"], 142), // UTC+5:45
+    
+    // Southeast Asia (UTC+6 to UTC+7)
+    (&["Asia/Dhaka", "Asia/Almaty"], 150),
+    (&["Asia/Bishkek", "Asia/Thimphu"], 150),
+    (&["Asia/Novosibirsk"], 150),
+    (&["Asia/Yangon"], 157), // UTC+6:30
+    (&["Asia/Bangkok", "Asia/Ho_Chi_Minh", "Asia/Phnom_Penh", "Asia/Vientiane"], 165),
+    (&["Asia/Hanoi"], 165),
+    (&["Asia/Jakarta", "Asia/Pontianak"], 165),
+    (&["Asia/Krasnoyarsk"], 165),
+    
+    // East Asia (UTC+7 to UTC+9)
+    (&["Asia/Shanghai", "Asia/Beijing", "Asia/Hong_Kong", "Asia/Macau"], 180),
+    (&["Asia/Taipei", "Asia/Ulaanbaatar"], 180),
+    (&["Asia/Singapore", "Asia/Kuala_Lumpur"], 180),
+    (&["Asia/Manila", "Asia/Makassar"], 180),
+    (&["Asia/Irkutsk"], 180),
+    (&["Asia/Seoul", "Asia/Tokyo"], 195),
+    (&["Asia/Pyongyang"], 195),
+    (&["Asia/Yakutsk"], 195),
+    
+    // Australia/Oceania (UTC+8 to UTC+12)
+    (&["Australia/Perth"], 180),
+    (&["Australia/Eucla"], 187), // UTC+8:45
+    (&["Asia/Jayapura"], 195),
+    (&["Australia/Darwin"], 195),
+    (&["Australia/Adelaide"], 195),
+    (&["Australia/Brisbane", "Australia/Lindeman"], 195),
+    (&["Australia/Sydney", "Australia/Melbourne", "Australia/Hobart"], 195),
+    (&["Australia/Canberra"], 195),
+    (&["Pacific/Port_Moresby", "Pacific/Palau", "Pacific/Chuuk", "Pacific/Guam", "Pacific/Saipan"], 195),
+    (&["Australia/Lord_Howe"], 202), // UTC+10:30
+    (&["Pacific/Guadalcanal", "Pacific/Noumea"], 210),
+    (&["Pacific/Norfolk"], 210),
+    (&["Pacific/Fiji", "Pacific/Tarawa"], 240),
+    (&["Pacific/Funafuti", "Pacific/Wake"], 240),
+    (&["Pacific/Auckland", "Pacific/Majuro"], 255),
+    (&["Pacific/Wellington"], 255),
+    (&["Pacific/Chatham"], 268), // UTC+12:45
+    (&["Pacific/Apia", "Pacific/Fakaofo"], 255),
+    (&["Pacific/Tongatapu"], 255),
+    
+    // North America - Pacific (UTC-8 to UTC-7)
+    (&["America/Los_Angeles", "America/Vancouver", "America/Tijuana"], -480),
+    (&["US/Pacific"], -480),
+    (&["America/Whitehorse", "America/Dawson"], -480),
+    (&["America/Phoenix", "America/Hermosillo"], -420), // No DST
+    (&["America/Denver", "America/Edmonton", "America/Boise"], -420),
+    (&["US/Mountain"], -420),
+    (&["America/Ciudad_Juarez", "America/Ojinaga"], -420),
+    
+    // North America - Central (UTC-6)
+    (&["America/Chicago", "America/Winnipeg", "America/Rainy_River"], -360),
+    (&["America/Matamoros", "America/Mexico_City", "America/Monterrey"], -360),
+    (&["US/Central", "America/Managua"], -360),
+    (&["America/Regina", "America/Swift_Current"], -360), // No DST
+    
+    // North America - Eastern (UTC-5)
+    (&["America/New_York", "America/Toronto", "America/Montreal"], -300),
+    (&["America/Detroit", "America/Indiana/Indianapolis"], -300),
+    (&["America/Cancun", "America/Havana", "America/Port-au-Prince"], -300),
+    (&["America/Nassau", "America/Jamaica"], -300),
+    (&["America/Panama", "America/Bogota", "America/Lima"], -300),
+    (&["US/Eastern", "America/Guayaquil", "America/Quito"], -300),
+    
+    // South America (UTC-5 to UTC-3)
+    (&["America/Caracas"], -270), // UTC-4:30
+    (&["America/Santiago", "America/Asuncion"], -240),
+    (&["America/La_Paz", "America/Guyana"], -240),
+    (&["America/Santo_Domingo", "America/San_Juan", "America/Georgetown"], -240),
+    (&["America/Argentina/Buenos_Aires", "America/Montevideo"], -180),
+    (&["America/Sao_Paulo", "America/Fortaleza"], -180),
+    (&["America/Buenos_Aires", "America/Rio_de_Janeiro", "America/Paramaribo", "America/Cayenne"], -180),
+    (&["America/Nuuk", "America/Miquelon"], -180),
+    (&["America/Godthab"], -180),
+    (&["America/St_Johns"], -210), // UTC-3:30
+    
+    // Africa (Various)
+    (&["America/Noronha"], -120), // UTC-2
+    (&["Atlantic/South_Georgia"], -120),
+    (&["Atlantic/Cape_Verde"], -60),
+    (&["Africa/Abidjan", "Africa/Accra", "Africa/Bamako"], -30),
+    (&["Africa/Dakar", "Africa/Ouagadougou", "Africa/Conakry", "Africa/Freetown", "Africa/Monrovia"], -30),
+    (&["Africa/Lome", "Africa/Nouakchott", "Africa/Banjul", "Africa/Guinea-Bissau", "Africa/Sao_Tome"], -30),
+    (&["Africa/Lagos", "Africa/Kinshasa", "Africa/Porto-Novo", "Africa/Niamey", "Africa/Ndjamena"], 0),
+    (&["Africa/Bangui", "Africa/Brazzaville", "Africa/Luanda", "Africa/Douala", "Africa/Libreville"], 0),
+    (&["Africa/Malabo"], 0),
+    (&["Africa/Tripoli"], 0),
+    (&["Africa/Cape_Town", "Africa/Harare", "Africa/Maputo", "Africa/Lusaka", "Africa/Blantyre"], 30),
+    (&["Africa/Gaborone", "Africa/Maseru", "Africa/Mbabane", "Africa/Kigali", "Africa/Bujumbura"], 30),
+    (&["Africa/Khartoum"], 30),
+    (&["Africa/Nairobi", "Africa/Addis_Ababa", "Africa/Dar_es_Salaam", "Africa/Mogadishu"], 45),
+    (&["Africa/Djibouti", "Africa/Asmara", "Africa/Kampala"], 45),
+    (&["Africa/Port_Louis", "Africa/Victoria"], 60), // Mauritius, Seychelles
+    
+    // Pacific Islands
+    (&["Pacific/Honolulu"], -600),
+    (&["Pacific/Marquesas"], -570), // UTC-9:30
+    (&["Pacific/Gambier"], -540),
+    (&["Pacific/Pitcairn"], -480),
+    (&["Pacific/Easter"], -360),
+    (&["Pacific/Galapagos"], -360),
+    (&["Pacific/Tahiti"], -600),
+    (&["America/Anchorage"], -540),
+    (&["America/Honolulu"], -600),
+    (&["Pacific/Rarotonga"], -600),
+    (&["Pacific/Niue"], -660),
+    
+    // Antarctic stations (time of their supply base)
+    (&["Antarctica/McMurdo"], 255),
+    (&["Antarctica/Casey"], 180),
+    (&["Antarctica/Davis"], 165),
+    (&["Antarctica/Mawson", "Antarctica/Vostok"], 120),
+    (&["Antarctica/Palmer", "Antarctica/Rothera"], -180),
+    (&["Antarctica/Syowa"], 45),
+    (&["Antarctica/Troll"], -30),
+];
+
+/// Get approximate longitude offset for timezone (in minutes)
+pub fn get_longitude_offset(timezone: &str) -> i32 {
+    // Default to Central Europe
+    longitude_offset_entry(timezone).unwrap_or(0)
+}
+
+// Offset listed for the timezone, if any
+fn longitude_offset_entry(timezone: &str) -> Option<i32> {
+    LONGITUDE_OFFSETS
+        .iter()
+        .find(|(zones, _)| zones.contains(&timezone))
+        .map(|&(_, offset)| offset)
+}
+
+/// Timezones listed in the longitude offset table, in table order
+pub fn offset_timezones() -> impl Iterator<Item = &'static str> {
+    LONGITUDE_OFFSETS.iter().flat_map(|&(zones, _)| zones.iter().copied())
+}
+
+/// Summary of the system timezone setting that `guess_location_from_system`
+/// reads: the TZ variable, /etc/timezone and the /etc/localtime link, which
+/// timedatectl and the distribution tools rewrite on a change. Reading them
+/// costs no more than a couple of small reads.
+pub fn system_timezone_key() -> String {
+    timezone_key(&env::var("TZ").unwrap_or_default(), Path::new("/etc/timezone"), Path::new("/etc/localtime"))
+}
+
+// Key of `system_timezone_key` for the given TZ value and files. The link is
+// kept as written and resolved, as `guess_location_from_system` tries both.
+fn timezone_key(tz: &str, timezone_file: &Path, localtime: &Path) -> String {
+    let timezone = fs::read_to_string(timezone_file).unwrap_or_default();
+    let link = fs::read_link(localtime).unwrap_or_default();
+    let resolved = fs::canonicalize(localtime).unwrap_or_default();
+    format!(
+        "TZ={} timezone={} localtime={} resolved={}",
+        tz,
+        timezone.trim(),
+        link.display(),
+        resolved.display()
+    )
+}
+
+/// Try to guess location from timezone
+pub fn guess_location_from_system() -> Option<(String, String)> {
+    // The TZ environment variable overrides the system setting, as for
+    // every other program (and the local clock)
+    if let Ok(value) = env::var("TZ") {
+        if let Some(tz) = tz_variable_timezone(&value) {
+            if let Some(name) = timezone_to_location_name(tz) {
+                return Some((tz.to_string(), name));
+            }
+        }
+    }
+
+    // Then /etc/timezone
+    if let Ok(content) = fs::read_to_string("/etc/timezone") {
+        let tz = content.trim();
+        if let Some(name) = timezone_to_location_name(tz) {
+            return Some((tz.to_string(), name));
+        }
+    }
+    
+    // Try the symbolic link /etc/localtime, as written (possibly relative,
+    // "../usr/share/zoneinfo/Europe/Brussels"), then fully resolved
+    let link = fs::read_link("/etc/localtime").ok();
+    let resolved = fs::canonicalize("/etc/localtime").ok();
+    for target in link.iter().chain(resolved.iter()) {
+        if let Some(tz) = zoneinfo_timezone(target) {
+            if let Some(name) = timezone_to_location_name(tz) {
+                return Some((tz.to_string(), name));
+            }
+        }
+    }
+    
+    None
+}
+
+// Timezone name of a TZ value: "Europe/Paris", ":Europe/Paris" or a path
+// into the zoneinfo database. POSIX rules ("CET-1CEST") name no location.
+fn tz_variable_timezone(value: &str) -> Option<&str> {
+    let value = value.strip_prefix(':').unwrap_or(value).trim();
+    if value.starts_with('/') {
+        zoneinfo_timezone(Path::new(value))
+    } else {
+        Some(value).filter(|tz| !tz.is_empty())
+    }
+}
+
+// Timezone name after the last "zoneinfo" component of a path, wherever the
+// database lives (/usr/share/zoneinfo, /etc/zoneinfo, /usr/lib/zoneinfo...)
+fn zoneinfo_timezone(path: &Path) -> Option<&str> {
+    let path = path.to_str()?;
+    let start = path.rfind("zoneinfo/")? + "zoneinfo/".len();
+    Some(&path[start..]).filter(|tz| !tz.is_empty())
+}
+
+// Location name of each timezone
+const LOCATION_NAMES: &[(&str, &str)] = &[
+    // Europe
+    ("Europe/Paris", "Paris, France"),
+    ("Europe/Brussels", "Brussels, Belgium"),
+    ("Europe/London", "London, United Kingdom"),
+    ("Europe/Berlin", "Berlin, Germany"),
+    ("Europe/Madrid", "Madrid, Spain"),
+    ("Europe/Rome", "Rome, Italy"),
+    ("Europe/Amsterdam", "Amsterdam, Netherlands"),
+    ("Europe/Lisbon", "Lisbon, Portugal"),
+    ("Europe/Vienna", "Vienna, Austria"),
+    ("Europe/Zurich", "Zurich, Switzerland"),
+    ("Europe/Warsaw", "Warsaw, Poland"),
+    ("Europe/Prague", "Prague, Czech Republic"),
+    ("Europe/Stockholm", "Stockholm, Sweden"),
+    ("Europe/Oslo", "Oslo, Norway"),
+    ("Europe/Copenhagen", "Copenhagen, Denmark"),
+    ("Europe/Helsinki", "Helsinki, Finland"),
+    ("Europe/Moscow", "Moscow, Russia"),
+    ("Europe/Kiev", "Kyiv, Ukraine"),
+    ("Europe/Bucharest", "Bucharest, Romania"),
+    ("Europe/Budapest", "Budapest, Hungary"),
+    ("Europe/Athens", "Athens, Greece"),
+    ("Europe/Dublin", "Dublin, Ireland"),
+    ("Europe/Sofia", "Sofia, Bulgaria"),
+    ("Europe/Belgrade", "Belgrade, Serbia"),
+    ("Europe/Zagreb", "Zagreb, Croatia"),
+    ("Europe/Sarajevo", "Sarajevo, Bosnia and Herzegovina"),
+    ("Europe/Skopje", "Skopje, North Macedonia"),
+    ("Europe/Tirane", "Tirana, Albania"),
+    ("Europe/Minsk", "Minsk, Belarus"),
+    ("Europe/Riga", "Riga, Latvia"),
+    ("Europe/Vilnius", "Vilnius, Lithuania"),
+    ("Europe/Tallinn", "Tallinn, Estonia"),
+    ("Europe/Chisinau", "Chisinau, Moldova"),
+    ("Europe/Bratislava", "Bratislava, Slovakia"),
+    ("Europe/Ljubljana", "Ljubljana, Slovenia"),
+    ("Europe/Luxembourg", "Luxembourg City, Luxembourg"),
+    ("Europe/Valletta", "Valletta, Malta"),
+    ("Europe/Monaco", "Monaco"),
+    ("Europe/San_Marino", "San Marino"),
+    ("Europe/Vatican", "Vatican City"),
+    ("Europe/Andorra", "Andorra la Vella, Andorra"),
+    ("Europe/Istanbul", "Istanbul, Turkey"),
+    ("Europe/Nicosia", "Nicosia, Cyprus"),
+    
+    // North America
+    ("America/New_York", "New York City, USA"),
+    ("US/Eastern", "New York City, USA"),
+    ("America/Chicago", "Chicago, USA"),
+    ("US/Central", "Chicago, USA"),
+    ("America/Denver", "Denver, USA"),
+    ("US/Mountain", "Denver, USA"),
+    ("America/Los_Angeles", "Los Angeles, USA"),
+    ("US/Pacific", "Los Angeles, USA"),
+    ("America/Phoenix", "Phoenix, USA"),
+    ("America/Anchorage", "Anchorage, USA"),
+    ("America/Honolulu", "Honolulu, USA"),
+    ("America/Toronto", "Toronto, Canada"),
+    ("America/Vancouver", "Vancouver, Canada"),
+    ("America/Montreal", "Montreal, Canada"),
+    ("America/Winnipeg", "Winnipeg, Canada"),
+    ("America/Edmonton", "Edmonton, Canada"),
+    ("America/Mexico_City", "Mexico City, Mexico"),
+    ("America/Cancun", "Cancun, Mexico"),
+    ("America/Havana", "Havana, Cuba"),
+    ("America/Port-au-Prince", "Port-au-Prince, Haiti"),
+    ("America/Santo_Domingo", "Santo Domingo, Dominican Republic"),
+    ("America/San_Juan", "San Juan, Puerto Rico"),
+    ("America/Nassau", "Nassau, Bahamas"),
+    ("America/Jamaica", "Kingston, Jamaica"),
+    ("America/Managua", "Managua, Nicaragua"),
+    ("America/Panama", "Panama City, Panama"),
+    ("America/Bogota", "Bogota, Colombia"),
+    ("America/Lima", "Lima, Peru"),
+    ("America/Caracas", "Caracas, Venezuela"),
+    ("America/Georgetown", "Georgetown, Guyana"),
+    ("America/Paramaribo", "Paramaribo, Suriname"),
+    
+    // South America
+    ("America/Santiago", "Santiago, Chile"),
+    ("America/Buenos_Aires", "Buenos Aires, Argentina"),
+    ("America/Sao_Paulo", "Sao Paulo, Brazil"),
+    ("America/Rio_de_Janeiro", "Rio de Janeiro, Brazil"),
+    ("America/Fortaleza", "Fortaleza, Brazil"),
+    ("America/Asuncion", "Asuncion, Paraguay"),
+    ("America/Montevideo", "Montevideo, Uruguay"),
+    ("America/La_Paz", "La Paz, Bolivia"),
+    ("America/Guayaquil", "Guayaquil, Ecuador"),
+    ("America/Quito", "Quito, Ecuador"),
+    ("America/Cayenne", "Cayenne, French Guiana"),
+    
+    // Asia
+    ("Asia/Tokyo", "Tokyo, Japan"),
+    ("Asia/Shanghai", "Shanghai, China"),
+    ("Asia/Beijing", "Beijing, China"),
+    ("Asia/Hong_Kong", "Hong Kong"),
+    ("Asia/Macau", "Macau"),
+    ("Asia/Taipei", "Taipei, Taiwan"),
+    ("Asia/Seoul", "Seoul, South Korea"),
+    ("Asia/Pyongyang", "Pyongyang, North Korea"),
+    ("Asia/Ulaanbaatar", "Ulaanbaatar, Mongolia"),
+    ("Asia/Singapore", "Singapore"),
+    ("Asia/Kuala_Lumpur", "Kuala Lumpur, Malaysia"),
+    ("Asia/Jakarta", "Jakarta, Indonesia"),
+    ("Asia/Bangkok", "Bangkok, Thailand"),
+    ("Asia/Manila", "Manila, Philippines"),
+    ("Asia/Ho_Chi_Minh", "Ho Chi Minh City, Vietnam"),
+    ("Asia/Hanoi", "Hanoi, Vietnam"),
+    ("Asia/Phnom_Penh", "Phnom Penh, Cambodia"),
+    ("Asia/Vientiane", "Vientiane, Laos"),
+    ("Asia/Yangon", "Yangon, Myanmar"),
+    ("Asia/Dhaka", "Dhaka, Bangladesh"),
+    ("Asia/Kolkata", "Kolkata, India"),
+    ("Asia/Delhi", "New Delhi, India"),
+    ("Asia/Mumbai", "Mumbai, India"),
+    ("Asia/Chennai", "Chennai, India"),
+    ("Asia/Karachi", "Karachi, Pakistan"),
+    ("Asia/Lahore", "Lahore, Pakistan"),
+    ("Asia/Kabul", "Kabul, Afghanistan"),
+    ("Asia/Tehran", "Tehran, Iran"),
+    ("Asia/Baghdad", "Baghdad, Iraq"),
+    ("Asia/Riyadh", "Riyadh, Saudi Arabia"),
+    ("Asia/Dubai", "Dubai, UAE"),
+    ("Asia/Muscat", "Muscat, Oman"),
+    ("Asia/Doha", "Doha, Qatar"),
+    ("Asia/Kuwait", "Kuwait City, Kuwait"),
+    ("Asia/Bahrain", "Manama, Bahrain"),
+    ("Asia/Amman", "Amman, Jordan"),
+    ("Asia/Beirut", "Beirut, Lebanon"),
+    ("Asia/Damascus", "Damascus, Syria"),
+    ("Asia/Jerusalem", "Jerusalem, Israel"),
+    ("Asia/Gaza", "Palestine"),
+    ("Asia/Hebron", "Palestine"),
+    ("Asia/Yerevan", "Yerevan, Armenia"),
+    ("Asia/Baku", "Baku, Azerbaijan"),
+    ("Asia/Tbilisi", "Tbilisi, Georgia"),
+    ("Asia/Ashgabat", "Ashgabat, Turkmenistan"),
+    ("Asia/Tashkent", "Tashkent, Uzbekistan"),
+    ("Asia/Dushanbe", "Dushanbe, Tajikistan"),
+    ("Asia/Bishkek", "Bishkek, Kyrgyzstan"),
+    ("Asia/Almaty", "Almaty, Kazakhstan"),
+    ("Asia/Colombo", "Colombo, Sri Lanka"),
+    ("Asia/Kathmandu", "Kathmandu, Nepal"),
+    ("Asia/Thimphu", "Thimphu, Bhutan"),
+    ("Asia/Male", "Male, Maldives"),
+    
+    // Africa
+    ("Africa/Cairo", "Cairo, Egypt"),
+    ("Africa/Johannesburg", "Johannesburg, South Africa"),
+    ("Africa/Cape_Town", "Cape Town, South Africa"),
+    ("Africa/Lagos", "Lagos, Nigeria"),
+    ("Africa/Kinshasa", "Kinshasa, DR Congo"),
+    ("Africa/Nairobi", "Nairobi, Kenya"),
+    ("Africa/Addis_Ababa", "Addis Ababa, Ethiopia"),
+    ("Africa/Dar_es_Salaam", "Dar es Salaam, Tanzania"),
+    ("Africa/Khartoum", "Khartoum, Sudan"),
+    ("Africa/Algiers", "Algiers, Algeria"),
+    ("Africa/Casablanca", "Casablanca, Morocco"),
+    ("Africa/Tunis", "Tunis, Tunisia"),
+    ("Africa/Tripoli", "Tripoli, Libya"),
+    ("Africa/Accra", "Accra, Ghana"),
+    ("Africa/Dakar", "Dakar, Senegal"),
+    ("Africa/Abidjan", "Abidjan, Ivory Coast"),
+    ("Africa/Bamako", "Bamako, Mali"),
+    ("Africa/Ouagadougou", "Ouagadougou, Burkina Faso"),
+    ("Africa/Conakry", "Conakry, Guinea"),
+    ("Africa/Freetown", "Freetown, Sierra Leone"),
+    ("Africa/Monrovia", "Monrovia, Liberia"),
+    ("Africa/Lome", "Lome, Togo"),
+    ("Africa/Porto-Novo", "Porto-Novo, Benin"),
+    ("Africa/Niamey", "Niamey, Niger"),
+    ("Africa/Ndjamena", "Ndjamena, Chad"),
+    ("Africa/Bangui", "Bangui, Central African Republic"),
+    ("Africa/Brazzaville", "Brazzaville, Republic of the Congo"),
+    ("Africa/Luanda", "Luanda, Angola"),
+    ("Africa/Lusaka", "Lusaka, Zambia"),
+    ("Africa/Harare", "Harare, Zimbabwe"),
+    ("Africa/Maputo", "Maputo, Mozambique"),
+    ("Africa/Blantyre", "Blantyre, Malawi"),
+    ("Africa/Gaborone", "Gaborone, Botswana"),
+    ("Africa/Maseru", "Maseru, Lesotho"),
+    ("Africa/Mbabane", "Mbabane, Eswatini"),
+    ("Africa/Mogadishu", "Mogadishu, Somalia"),
+    ("Africa/Djibouti", "Djibouti City, Djibouti"),
+    ("Africa/Asmara", "Asmara, Eritrea"),
+    ("Africa/Bujumbura", "Bujumbura, Burundi"),
+    ("Africa/Kigali", "Kigali, Rwanda"),
+    ("Africa/Kampala", "Kampala, Uganda"),
+    ("Africa/Douala", "Douala, Cameroon"),
+    ("Africa/Libreville", "Libreville, Gabon"),
+    ("Africa/Malabo", "Malabo, Equatorial Guinea"),
+    ("Africa/Sao_Tome", "Sao Tome, Sao Tome and Principe"),
+    ("Africa/Windhoek", "Windhoek, Namibia"),
+    ("Africa/Port_Louis", "Port Louis, Mauritius"),
+    ("Africa/Victoria", "Victoria, Seychelles"),
+    ("Africa/Nouakchott", "Nouakchott, Mauritania"),
+    ("Africa/Banjul", "Banjul, Gambia"),
+    ("Africa/Guinea-Bissau", "Bissau, Guinea-Bissau"),
+    
+    // Australia/Oceania
+    ("Australia/Sydney", "Sydney, Australia"),
+    ("Australia/Melbourne", "Melbourne, Australia"),
+    ("Australia/Brisbane", "Brisbane, Australia"),
+    ("Australia/Perth", "Perth, Australia"),
+    ("Australia/Adelaide", "Adelaide, Australia"),
+    ("Australia/Hobart", "Hobart, Australia"),
+    ("Australia/Darwin", "Darwin, Australia"),
+    ("Australia/Canberra", "Canberra, Australia"),
+    ("Pacific/Auckland", "Auckland, New Zealand"),
+    ("Pacific/Wellington", "Wellington, New Zealand"),
+    ("Pacific/Fiji", "Suva, Fiji"),
+    ("Pacific/Port_Moresby", "Port Moresby, Papua New Guinea"),
+    ("Pacific/Guadalcanal", "Honiara, Solomon Islands"),
+    ("Pacific/Noumea", "Noumea, New Caledonia"),
+    ("Pacific/Tarawa", "Tarawa, Kiribati"),
+    ("Pacific/Majuro", "Majuro, Marshall Islands"),
+    ("Pacific/Palau", "Ngerulmud, Palau"),
+    ("Pacific/Chuuk", "Chuuk, Micronesia"),
+    ("Pacific/Guam", "Hagatna, Guam"),
+    ("Pacific/Saipan", "Saipan, Northern Mariana Islands"),
+    ("Pacific/Honolulu", "Honolulu, Hawaii, USA"),
+    ("Pacific/Tahiti", "Papeete, French Polynesia"),
+    ("Pacific/Rarotonga", "Avarua, Cook Islands"),
+    ("Pacific/Apia", "Apia, Samoa"),
+    ("Pacific/Niue", "Alofi, Niue"),
+    ("Pacific/Tongatapu", "Nuku'alofa, Tonga"),
+    ("Pacific/Funafuti", "Funafuti, Tuvalu"),
+    ("Pacific/Wake", "Wake Island, USA"),
+    ("Pacific/Easter", "Easter Island, Chile"),
+    
+    // Antarctica (for completeness)
+    ("Antarctica/McMurdo", "McMurdo Station, Antarctica"),
+    ("Antarctica/Casey", "Casey Station, Antarctica"),
+    ("Antarctica/Davis", "Davis Station, Antarctica"),
+    ("Antarctica/Mawson", "Mawson Station, Antarctica"),
+    ("Antarctica/Palmer", "Palmer Station, Antarctica"),
+    ("Antarctica/Rothera", "Rothera Station, Antarctica"),
+    ("Antarctica/Syowa", "Syowa Station, Antarctica"),
+    ("Antarctica/Troll", "Troll Station, Antarctica"),
+    ("Antarctica/Vostok", "Vostok Station, Antarctica"),
+];
+
+// Generic fallbacks for regions, by timezone prefix
+const REGION_NAMES: &[(&str, &str)] = &[
+    ("Europe/", "Europe"),
+    ("America/", "Americas"),
+    ("Asia/", "Asia"),
+    ("Africa/", "Africa"),
+    ("Australia/", "Australia"),
+    ("Pacific/", "Pacific Islands"),
+    ("Atlantic/", "Atlantic Region"),
+    ("Indian/", "Indian Ocean Region"),
+    ("Antarctica/", "Antarctica"),
+];
+
+/// Extended database mapping timezones to location names
+pub fn timezone_to_location_name(timezone: &str) -> Option<String> {
+    let name = LOCATION_NAMES
+        .iter()
+        .find(|&&(zone, _)| zone == timezone)
+        .or_else(|| REGION_NAMES.iter().find(|&&(prefix, _)| timezone.starts_with(prefix)))
+        .map(|&(_, name)| name)?;
+    Some(name.to_string())
+}
+
+/// Timezones with their own entry in the location name table (not only a
+/// region fallback), in table order
+pub fn named_timezones() -> impl Iterator<Item = &'static str> {
+    LOCATION_NAMES.iter().map(|&(zone, _)| zone)
+}
+
+/// Bundled (latitude, longitude) of the city named after `timezone`, if any
+pub fn timezone_coordinates(timezone: &str) -> Option<(f64, f64)> {
+    CITY_COORDINATES
+        .iter()
+        .find(|&&(zone, _, _)| zone == timezone)
+        .map(|&(_, latitude, longitude)| (latitude, longitude))
+}
+
+/// A city found by `find_city`
+#[derive(Debug, Clone, PartialEq)]
+pub struct City {
+    pub timezone: &'static str,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Look a city up by name or timezone ("tokyo", "Buenos Aires",
+/// "America/Sao_Paulo"), ignoring case. A partial name must match a single
+/// city, unless one of the matches is exact.
+pub fn find_city(query: &str) -> Result<City, String> {
+    let wanted = normalize_city(query);
+    if wanted.is_empty() {
+        return Err("empty city name".to_string());
+    }
+
+    let cities: Vec<City> = CITY_COORDINATES
+        .iter()
+        .filter_map(|&(timezone, latitude, longitude)| {
+            let name = timezone_to_location_name(timezone)?;
+            Some(City { timezone, name, latitude, longitude })
+        })
+        .collect();
+
+    // "Paris" or "europe/paris" in full
+    let exact = |city: &&City| {
+        let town = city.name.split(',').next().unwrap_or_default();
+        normalize_city(town) == wanted || normalize_city(city.timezone) == wanted
+    };
+    if let Some(city) = cities.iter().find(exact) {
+        return Ok(city.clone());
+    }
+
+    let matches: Vec<&City> = cities
+        .iter()
+        .filter(|city| normalize_city(&city.name).contains(&wanted) || normalize_city(city.timezone).contains(&wanted))
+        .collect();
+    match matches.as_slice() {
+        [] => Err(format!("unknown city '{}'", query)),
+        [city] => Ok((*city).clone()),
+        several => {
+            let names: Vec<&str> = several.iter().map(|city| city.name.as_str()).collect();
+            Err(format!("'{}' matches several cities: {}", query, names.join("; ")))
+        }
+    }
+}
+
+// Lowercase, with the underscores of timezone names as spaces
+fn normalize_city(name: &str) -> String {
+    name.trim().to_lowercase().replace('_', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timezone_key_follows_the_system_files() {
+        let dir = env::temp_dir().join(format!("colorwarm-timezone-{}", std::process::id()));
+        fs::create_dir_all(dir.join("zoneinfo/Europe")).unwrap();
+        fs::write(dir.join("zoneinfo/Europe/Paris"), "").unwrap();
+        fs::write(dir.join("zoneinfo/Europe/Berlin"), "").unwrap();
+        let (timezone, localtime) = (dir.join("timezone"), dir.join("localtime"));
+
+        fs::write(&timezone, "Europe/Paris\n").unwrap();
+        std::os::unix::fs::symlink("zoneinfo/Europe/Paris", &localtime).unwrap();
+        let paris = timezone_key("", &timezone, &localtime);
+        assert!(paris.contains("timezone=Europe/Paris "), "{}", paris);
+
+        // Debian style: /etc/timezone rewritten, the link left alone
+        fs::write(&timezone, "Europe/Berlin\n").unwrap();
+        let berlin = timezone_key("", &timezone, &localtime);
+        assert_ne!(berlin, paris);
+
+        // Link through another one, which is the one moved
+        fs::remove_file(&localtime).unwrap();
+        std::os::unix::fs::symlink("zoneinfo/Europe/Paris", dir.join("current")).unwrap();
+        std::os::unix::fs::symlink("current", &localtime).unwrap();
+        let before = timezone_key("", &timezone, &localtime);
+        fs::remove_file(dir.join("current")).unwrap();
+        std::os::unix::fs::symlink("zoneinfo/Europe/Berlin", dir.join("current")).unwrap();
+        assert_ne!(timezone_key("", &timezone, &localtime), before);
+        assert_ne!(timezone_key("Europe/Rome", &timezone, &localtime), timezone_key("", &timezone, &localtime));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // A named timezone without an offset silently gets the Brussels times
+    #[test]
+    fn named_timezones_have_offsets() {
+        let missing: Vec<&str> = named_timezones()
+            .filter(|timezone| longitude_offset_entry(timezone).is_none())
+            .collect();
+        assert!(missing.is_empty(), "no longitude offset for {:?}", missing);
+    }
+
+    // With a timezone listed twice, only the first offset would ever be used
+    #[test]
+    fn offsets_listed_once() {
+        let mut seen = std::collections::HashSet::new();
+        let duplicates: Vec<&str> = offset_timezones().filter(|timezone| !seen.insert(*timezone)).collect();
+        assert!(duplicates.is_empty(), "listed more than once: {:?}", duplicates);
+    }
+
+    #[test]
+    fn known_offsets() {
+        assert_eq!(get_longitude_offset("Africa/Algiers"), 0);
+        assert_eq!(get_longitude_offset("Africa/Tunis"), 0);
+        assert_eq!(get_longitude_offset("Africa/Windhoek"), 30);
+        assert_eq!(get_longitude_offset("Africa/Johannesburg"), 30);
+        assert_eq!(get_longitude_offset("Asia/Kolkata"), 135);
+        assert_eq!(get_longitude_offset("Unknown/Zone"), 0);
+    }
+
+    #[test]
+    fn zoneinfo_link_targets() {
+        fn tz(path: &str) -> Option<&str> {
+            zoneinfo_timezone(Path::new(path))
+        }
+        assert_eq!(tz("/usr/share/zoneinfo/Europe/Brussels"), Some("Europe/Brussels"));
+        assert_eq!(tz("../usr/share/zoneinfo/Europe/Paris"), Some("Europe/Paris"));
+        assert_eq!(tz("/etc/zoneinfo/America/Argentina/Buenos_Aires"), Some("America/Argentina/Buenos_Aires"));
+        assert_eq!(tz("/usr/share/zoneinfo/"), None);
+        assert_eq!(tz("/etc/localtime"), None);
+    }
+
+    #[test]
+    fn tz_variable_forms() {
+        assert_eq!(tz_variable_timezone("America/New_York"), Some("America/New_York"));
+        assert_eq!(tz_variable_timezone(":Europe/Paris"), Some("Europe/Paris"));
+        assert_eq!(tz_variable_timezone(":/usr/share/zoneinfo/Asia/Tokyo"), Some("Asia/Tokyo"));
+        assert_eq!(tz_variable_timezone(""), None);
+    }
+}
@@ -0,0 +1,54 @@
+// Location detected from the system timezone, kept across runs so that a
+// start does not resolve it again while the timezone setting is unchanged
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::state_dir;
+
+const FILE_NAME: &str = "location";
+
+fn path() -> Option<PathBuf> {
+    Some(state_dir()?.join(FILE_NAME))
+}
+
+/// Record a detected (timezone, location name), with the `key` of the
+/// system setting it was resolved from (one field per line)
+pub fn save(key: &str, location: &(String, String)) -> io::Result<()> {
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, format!("{}\n{}\n{}\n", key, location.0, location.1))
+}
+
+/// Location saved by a previous run, if it was resolved from the same `key`
+pub fn load(key: &str) -> Option<(String, String)> {
+    let content = fs::read_to_string(path()?).ok()?;
+    parse(&content, key)
+}
+
+fn parse(content: &str, key: &str) -> Option<(String, String)> {
+    let mut lines = content.lines();
+    if lines.next()? != key {
+        return None;
+    }
+    let timezone = lines.next().filter(|tz| !tz.is_empty())?;
+    let name = lines.next().filter(|name| !name.is_empty())?;
+    Some((timezone.to_string(), name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_key_is_ignored() {
+        let content = "localtime=Europe/Brussels\nEurope/Brussels\nBrussels, Belgium\n";
+        let location = Some(("Europe/Brussels".to_string(), "Brussels, Belgium".to_string()));
+        assert_eq!(parse(content, "localtime=Europe/Brussels"), location);
+        assert_eq!(parse(content, "localtime=Europe/Paris"), None);
+        assert_eq!(parse("localtime=Europe/Brussels\n", "localtime=Europe/Brussels"), None);
+    }
+}
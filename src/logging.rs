@@ -0,0 +1,105 @@
+// Leveled logging (log + env_logger) for the automatic mode and xsct -v
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use env_logger::{Builder, Env, WriteStyle};
+use log::{log, Level, LevelFilter};
+
+const REPEAT_MIN: Duration = Duration::from_secs(60); // First delay before a repeat is logged
+const REPEAT_MAX: Duration = Duration::from_secs(3600);
+
+/// Level names accepted by --log-level
+pub const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+/// Send log records to stderr, colored on a terminal unless `color` is false
+/// (daemon log files). An explicit level wins; otherwise RUST_LOG is used,
+/// defaulting to `default`.
+pub fn init(level: Option<LevelFilter>, default: LevelFilter, color: bool) {
+    let mut builder = match level {
+        Some(level) => {
+            let mut builder = Builder::new();
+            builder.filter_level(level);
+            builder
+        }
+        None => Builder::from_env(Env::default().default_filter_or(default.as_str())),
+    };
+    if !color {
+        builder.write_style(WriteStyle::Never);
+    }
+    // A second init (library callers with their own logger) is not an error
+    let _ = builder.try_init();
+}
+
+/// Keeps a message that comes back on every update (display gone, file not
+/// writable) from flooding the log. The first occurrence is logged; repeats
+/// of the same message are counted and logged after 1 minute, then after
+/// twice as long each time, up to an hour. `clear` ends the condition, so it
+/// is logged right away when it occurs again.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    repeats: RefCell<HashMap<&'static str, Repeat>>,
+}
+
+#[derive(Debug)]
+struct Repeat {
+    message: String,
+    next_log: Instant,
+    backoff: Duration,
+    suppressed: u32,
+}
+
+impl Throttle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log `message` at `level`, unless it repeats the last message logged
+    /// under `key` (one key per kind of problem) too soon
+    pub fn log(&self, key: &'static str, level: Level, message: String) {
+        let mut repeats = self.repeats.borrow_mut();
+        match repeats.get_mut(key) {
+            Some(repeat) if repeat.message == message && Instant::now() < repeat.next_log => {
+                repeat.suppressed += 1;
+            }
+            Some(repeat) if repeat.message == message => {
+                log!(level, "{} (repeated {} times)", message, repeat.suppressed + 1);
+                repeat.backoff = (repeat.backoff * 2).min(REPEAT_MAX);
+                repeat.next_log = Instant::now() + repeat.backoff;
+                repeat.suppressed = 0;
+            }
+            _ => {
+                log!(level, "{}", message);
+                let next_log = Instant::now() + REPEAT_MIN;
+                repeats.insert(key, Repeat { message, next_log, backoff: REPEAT_MIN, suppressed: 0 });
+            }
+        }
+    }
+
+    /// The problem logged under `key` is over
+    pub fn clear(&self, key: &'static str) {
+        self.repeats.borrow_mut().remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_are_counted_until_cleared() {
+        let throttle = Throttle::new();
+        for _ in 0..5 {
+            throttle.log("apply", Level::Warn, "display gone".to_string());
+        }
+        assert_eq!(throttle.repeats.borrow()["apply"].suppressed, 4);
+
+        // Another message under the same key is logged at once
+        throttle.log("apply", Level::Warn, "other".to_string());
+        assert_eq!(throttle.repeats.borrow()["apply"].suppressed, 0);
+
+        throttle.clear("apply");
+        assert!(throttle.repeats.borrow().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+// Prometheus metrics in the textfile format (--metrics-file), for the
+// node_exporter textfile collector
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::xsct::TempStatus;
+
+/// Values of the colorwarm_mode metric, one series each
+pub const MODES: [&str; 3] = ["day", "night", "transition"];
+
+/// Metrics of one update, `mode` being one of `MODES`
+pub fn format(temp: TempStatus, mode: &str) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "# HELP colorwarm_current_kelvin Color temperature applied to the screens.");
+    let _ = writeln!(text, "# TYPE colorwarm_current_kelvin gauge");
+    let _ = writeln!(text, "colorwarm_current_kelvin {}", temp.temp);
+    let _ = writeln!(text, "# HELP colorwarm_current_brightness Brightness applied to the screens, 0 to 1.");
+    let _ = writeln!(text, "# TYPE colorwarm_current_brightness gauge");
+    let _ = writeln!(text, "colorwarm_current_brightness {}", temp.brightness);
+    let _ = writeln!(text, "# HELP colorwarm_mode Part of the day, 1 for the current one.");
+    let _ = writeln!(text, "# TYPE colorwarm_mode gauge");
+    for name in MODES {
+        let _ = writeln!(text, "colorwarm_mode{{mode=\"{}\"}} {}", name, u8::from(name == mode));
+    }
+    text
+}
+
+/// Replace the metrics file. The text goes to "<path>.tmp" first and is
+/// renamed over the file, so the collector never reads a partial write
+/// (it only picks up *.prom files).
+pub fn write(path: &Path, temp: TempStatus, mode: &str) -> io::Result<()> {
+    let mut temporary = OsString::from(path.as_os_str());
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    fs::write(&temporary, format(temp, mode))?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn textfile_format() {
+        let text = format(TempStatus { temp: 4500, brightness: 0.8 }, "night");
+        assert!(text.contains("\ncolorwarm_current_kelvin 4500\n"));
+        assert!(text.contains("\ncolorwarm_current_brightness 0.8\n"));
+        assert!(text.contains("\ncolorwarm_mode{mode=\"night\"} 1\n"));
+        assert!(text.contains("\ncolorwarm_mode{mode=\"day\"} 0\n"));
+        assert!(text.ends_with('\n'));
+    }
+}
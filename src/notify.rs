@@ -0,0 +1,94 @@
+// Desktop notifications on day/transition/night changes (--notify), sent to
+// the org.freedesktop.Notifications D-Bus service of the session
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::Value;
+
+const SERVICE: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const APP_NAME: &str = "colorwarm";
+const ICON: &str = "weather-clear-night";
+const URGENCY_LOW: u8 = 0;
+const DEFAULT_TIMEOUT: i32 = -1; // Chosen by the notification server
+
+/// Sends a notification when the mode changes, replacing the previous one
+#[derive(Debug, Default)]
+pub struct Notifier {
+    mode: Cell<Option<&'static str>>,
+    // Id of the last notification, 0 before the first one
+    shown: Cell<u32>,
+    // Opened on the first notification, dropped after an error
+    conn: RefCell<Option<Connection>>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the mode of an update ("day", "night" or "transition") and
+    /// notify if it differs from the previous one. The first update only
+    /// records it, so starting or reloading does not notify.
+    pub fn update(&self, mode: &'static str, kelvin: i32) -> zbus::Result<()> {
+        if !self.changed(mode) {
+            return Ok(());
+        }
+        let result = self.send(summary(mode), &format!("Screen at {}K", kelvin));
+        if result.is_err() {
+            self.conn.replace(None);
+        }
+        result
+    }
+
+    // Whether `mode` differs from the last one seen, remembering it
+    fn changed(&self, mode: &'static str) -> bool {
+        self.mode.replace(Some(mode)).is_some_and(|previous| previous != mode)
+    }
+
+    fn send(&self, summary: &str, body: &str) -> zbus::Result<()> {
+        let open = self.conn.borrow().clone();
+        let conn = match open {
+            Some(conn) => conn,
+            None => {
+                let conn = Connection::session()?;
+                self.conn.replace(Some(conn.clone()));
+                conn
+            }
+        };
+        let proxy = Proxy::new(&conn, SERVICE, PATH, SERVICE)?;
+        let hints = HashMap::from([("urgency", Value::from(URGENCY_LOW))]);
+        let actions: Vec<&str> = Vec::new();
+        let id: u32 = proxy.call(
+            "Notify",
+            &(APP_NAME, self.shown.get(), ICON, summary, body, actions, hints, DEFAULT_TIMEOUT),
+        )?;
+        self.shown.set(id);
+        Ok(())
+    }
+}
+
+fn summary(mode: &str) -> &'static str {
+    match mode {
+        "night" => "Night temperature",
+        "day" => "Day temperature",
+        _ => "Screen temperature changing",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changes_notify() {
+        let notifier = Notifier::new();
+        assert!(!notifier.changed("day"));
+        assert!(!notifier.changed("day"));
+        assert!(notifier.changed("transition"));
+        assert!(!notifier.changed("transition"));
+        assert!(notifier.changed("night"));
+    }
+}
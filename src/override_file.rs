@@ -0,0 +1,50 @@
+// Temperature override through a file: while $XDG_RUNTIME_DIR/colorwarm.override
+// exists, the kelvin value it holds replaces the schedule. Lighter than the
+// control socket for scripts and keybindings (echo 3000 > ..., rm ...).
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::runtime_dir;
+use crate::xsct::TEMPERATURE_ZERO;
+
+const FILE_NAME: &str = "colorwarm.override";
+
+/// Path of the override file
+pub fn path() -> PathBuf {
+    runtime_dir().join(FILE_NAME)
+}
+
+/// Kelvin value of the override file, None when there is no file
+pub fn read(path: &Path) -> Result<Option<i32>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse(&content).map(Some),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("cannot read {}: {}", path.display(), e)),
+    }
+}
+
+fn parse(content: &str) -> Result<i32, String> {
+    let value = content.trim();
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|&kelvin| kelvin >= TEMPERATURE_ZERO)
+        .ok_or_else(|| format!("invalid temperature '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn override_values() {
+        assert_eq!(parse("3000\n"), Ok(3000));
+        assert_eq!(parse(" 4500 "), Ok(4500));
+        assert!(parse("").is_err());
+        assert!(parse("warm").is_err());
+        assert!(parse("-1").is_err());
+        assert_eq!(read(Path::new("/nonexistent/colorwarm.override")), Ok(None));
+    }
+}
@@ -0,0 +1,476 @@
+// Temperature curve over the day and clock helpers
+
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use chrono::{Datelike, Local, NaiveDateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::xsct::TEMPERATURE_ZERO;
+
+pub const MINUTES_PER_DAY: i32 = 24 * 60;
+const MIRED_SCALE: f64 = 1_000_000.0; // kelvin = MIRED_SCALE / mired
+
+/// Shape of the dawn/dusk ramps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CurveShape {
+    #[default]
+    Linear,
+    /// Eases in and out of the night and day temperatures
+    Cosine,
+}
+
+impl FromStr for CurveShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(CurveShape::Linear),
+            "cosine" => Ok(CurveShape::Cosine),
+            _ => Err(format!("unknown curve '{}' (expected linear or cosine)", s)),
+        }
+    }
+}
+
+/// Parameters of the temperature curve over the day
+#[derive(Debug, Clone)]
+pub struct CurveSettings {
+    pub day_temp: i32,
+    pub night_temp: i32,
+    /// Length of the dawn/dusk ramps in minutes; `None` ramps up to solar
+    /// noon and back down, without a day plateau
+    pub transition_minutes: Option<i32>,
+    /// Length of the morning and evening ramps when they differ from
+    /// `transition_minutes`
+    pub dawn_minutes: Option<i32>,
+    pub dusk_minutes: Option<i32>,
+    pub shape: CurveShape,
+    /// Night temperature while the sun is up and day temperature at night,
+    /// for people who sleep during the day
+    pub invert: bool,
+    /// (minute of the day, kelvin) points sorted by time; when not empty
+    /// they replace the sun-based curve
+    pub stops: Vec<(i32, i32)>,
+}
+
+/// Map a position along a ramp (0 = night, 1 = day) to the fraction of the
+/// temperature range to apply
+pub fn ease(position: f64, shape: CurveShape) -> f64 {
+    let position = position.clamp(0.0, 1.0);
+    match shape {
+        CurveShape::Linear => position,
+        CurveShape::Cosine => (1.0 - (std::f64::consts::PI * position).cos()) / 2.0,
+    }
+}
+
+/// Temperature for a time of day (minutes since midnight, may be fractional)
+pub fn target_kelvin(now_minutes: f64, sunrise: i32, sunset: i32, curve: &CurveSettings) -> i32 {
+    if !curve.stops.is_empty() {
+        return stops_kelvin(now_minutes, &curve.stops, curve.shape);
+    }
+    let kelvin = sun_kelvin(now_minutes, sunrise, sunset, curve);
+    if curve.invert {
+        // Mirror within the range: the ramps run the other way
+        curve.day_temp + curve.night_temp - kelvin
+    } else {
+        kelvin
+    }
+}
+
+// Interpolation between the stops around the given time, wrapping from the
+// last stop of the day to the first one
+fn stops_kelvin(now_minutes: f64, stops: &[(i32, i32)], shape: CurveShape) -> i32 {
+    let now = now_minutes.rem_euclid(MINUTES_PER_DAY as f64);
+    let next = stops.iter().position(|&(minute, _)| minute as f64 > now).unwrap_or(0);
+    let previous = (next + stops.len() - 1) % stops.len();
+    let (from_minute, from_kelvin) = stops[previous];
+    let (to_minute, to_kelvin) = stops[next];
+
+    // A single stop is a fixed temperature
+    let span = (to_minute - from_minute).rem_euclid(MINUTES_PER_DAY);
+    if span == 0 {
+        return from_kelvin;
+    }
+    let elapsed = (now - from_minute as f64).rem_euclid(MINUTES_PER_DAY as f64);
+    let fraction = ease(elapsed / span as f64, shape);
+    (from_kelvin as f64 + (to_kelvin - from_kelvin) as f64 * fraction).round() as i32
+}
+
+// Temperature following the sun: warm at night, cool in daylight
+fn sun_kelvin(now_minutes: f64, sunrise: i32, sunset: i32, curve: &CurveSettings) -> i32 {
+    // Polar day and polar night: no sunrise/sunset to ramp around
+    match polar_day(sunrise, sunset) {
+        Some(true) => return curve.day_temp,
+        Some(false) => return curve.night_temp,
+        None => {}
+    }
+
+    let range = (curve.day_temp - curve.night_temp) as f64;
+    let (sunrise_f, sunset_f) = (sunrise as f64, sunset as f64);
+    let now_minutes = align_to_daylight(now_minutes, sunrise, sunset);
+
+    // Calculate Kelvin value based on time of day
+    let kelvin = if now_minutes >= sunset_f || now_minutes < sunrise_f {
+        // Night: fixed night temperature
+        curve.night_temp as f64
+    } else {
+        // The ramps can't be longer than half the day
+        let half_day = (sunset_f - sunrise_f) / 2.0;
+        let ramp = |minutes: Option<i32>| {
+            minutes.or(curve.transition_minutes).map_or(half_day, |m| (m as f64).min(half_day))
+        };
+        // Position along a ramp, 0 at night and 1 from its end on
+        let position = |elapsed: f64, ramp: f64| if ramp <= 0.0 { 1.0 } else { elapsed / ramp };
+
+        // Rise after sunrise, plateau, fall before sunset
+        let morning = position(now_minutes - sunrise_f, ramp(curve.dawn_minutes));
+        let evening = position(sunset_f - now_minutes, ramp(curve.dusk_minutes));
+        curve.night_temp as f64 + range * ease(morning.min(evening), curve.shape)
+    };
+
+    // Limit values between night and day temperatures
+    (kelvin.round() as i32).clamp(curve.night_temp, curve.day_temp)
+}
+
+/// Move a time of day by a whole day when that puts it between sunrise and
+/// sunset. Daylight crosses midnight when the coordinates are far from the
+/// local timezone (Sydney on a UTC clock: sunrise -04:48, sunset 08:09).
+pub fn align_to_daylight(now_minutes: f64, sunrise: i32, sunset: i32) -> f64 {
+    let day = MINUTES_PER_DAY as f64;
+    let (sunrise, sunset) = (sunrise as f64, sunset as f64);
+    if now_minutes < sunrise && now_minutes + day < sunset {
+        now_minutes + day
+    } else if now_minutes >= sunset && now_minutes - day >= sunrise {
+        now_minutes - day
+    } else {
+        now_minutes
+    }
+}
+
+/// `Some(true)` when the sun never sets (24h of daylight), `Some(false)`
+/// when it never rises, `None` on a normal day
+pub fn polar_day(sunrise: i32, sunset: i32) -> Option<bool> {
+    let day_length = sunset - sunrise;
+    if day_length >= MINUTES_PER_DAY {
+        Some(true)
+    } else if day_length <= 0 {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Move sunset back to `latest_sunset` and sunrise forward to
+/// `earliest_sunrise` (minutes since midnight) when the sun is later or
+/// earlier. Each limit is taken on the day of the daylight it bounds, so it
+/// also works when daylight crosses midnight, and turns a polar day into an
+/// ordinary one.
+pub fn clamp_day_times(
+    sunrise: i32,
+    sunset: i32,
+    earliest_sunrise: Option<i32>,
+    latest_sunset: Option<i32>,
+) -> (i32, i32) {
+    let mut sunset = sunset;
+    if let Some(limit) = latest_sunset {
+        // First occurrence of the limit after sunrise
+        sunset = sunset.min(sunrise + (limit - sunrise - 1).rem_euclid(MINUTES_PER_DAY) + 1);
+    }
+    let mut sunrise = sunrise;
+    if let Some(limit) = earliest_sunrise {
+        // Last occurrence of the limit before sunset
+        sunrise = sunrise.max(sunset - MINUTES_PER_DAY + (limit - sunset).rem_euclid(MINUTES_PER_DAY));
+    }
+    (sunrise, sunset)
+}
+
+/// Parse a clock time given as HH:MM into minutes since midnight
+pub fn parse_clock_time(s: &str) -> Result<i32, String> {
+    let invalid = || format!("invalid time '{}' (expected HH:MM)", s);
+    let (hours, minutes) = s.split_once(':').ok_or_else(invalid)?;
+    let digits = |part: &str, max_len| {
+        (1..=max_len).contains(&part.len()) && part.bytes().all(|b| b.is_ascii_digit())
+    };
+    if !digits(hours, 2) || !digits(minutes, 2) || minutes.len() != 2 {
+        return Err(invalid());
+    }
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours >= 24 || minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+/// Check the day/night temperatures are displayable and correctly ordered
+pub fn validate_temperatures(day_temp: i32, night_temp: i32) -> Result<(), String> {
+    for (name, temp) in [("day", day_temp), ("night", night_temp)] {
+        if temp < TEMPERATURE_ZERO {
+            return Err(format!(
+                "{} temperature {}K is below the displayable minimum of {}K",
+                name, temp, TEMPERATURE_ZERO
+            ));
+        }
+    }
+    if night_temp >= day_temp {
+        return Err(format!(
+            "night temperature ({}K) must be lower than day temperature ({}K)",
+            night_temp, day_temp
+        ));
+    }
+    Ok(())
+}
+
+/// Convert mireds (micro reciprocal degrees) to the nearest Kelvin
+pub fn mired_to_kelvin(mired: f64) -> i32 {
+    (MIRED_SCALE / mired).round() as i32
+}
+
+/// Convert Kelvin to mireds
+pub fn kelvin_to_mired(kelvin: i32) -> f64 {
+    MIRED_SCALE / kelvin as f64
+}
+
+/// Source of the local time the schedule follows: the system clock, or a
+/// fixed one in tests
+pub trait Clock: fmt::Debug {
+    /// Wall clock time in `timezone`, or in the system timezone
+    fn now(&self, timezone: Option<Tz>) -> NaiveDateTime;
+
+    /// The same instant as a `SystemTime`, for deadlines such as the end of
+    /// a pause
+    fn system_time(&self) -> SystemTime;
+
+    /// Minutes since midnight
+    fn local_time(&self, timezone: Option<Tz>) -> i32 {
+        let now = self.now(timezone);
+        (now.hour() as i32) * 60 + (now.minute() as i32)
+    }
+
+    /// Fractional minutes since midnight
+    fn local_time_precise(&self, timezone: Option<Tz>) -> f64 {
+        self.now(timezone).num_seconds_from_midnight() as f64 / 60.0
+    }
+
+    /// Month (1-12) and day (1-31)
+    fn month_day(&self, timezone: Option<Tz>) -> (usize, i32) {
+        let now = self.now(timezone);
+        (now.month() as usize, now.day() as i32)
+    }
+
+    /// Minute of the hour (0-59)
+    fn minute(&self, timezone: Option<Tz>) -> u32 {
+        self.now(timezone).minute()
+    }
+}
+
+/// The real wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self, timezone: Option<Tz>) -> NaiveDateTime {
+        match timezone {
+            Some(timezone) => Utc::now().with_timezone(&timezone).naive_local(),
+            None => Local::now().naive_local(),
+        }
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock standing at a given local time, whatever the timezone, until
+/// moved with `set`. As a `SystemTime` that local time is taken as UTC.
+#[derive(Debug)]
+pub struct FixedClock(Cell<NaiveDateTime>);
+
+impl FixedClock {
+    pub fn new(now: NaiveDateTime) -> Self {
+        FixedClock(Cell::new(now))
+    }
+
+    pub fn set(&self, now: NaiveDateTime) {
+        self.0.set(now);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self, _timezone: Option<Tz>) -> NaiveDateTime {
+        self.0.get()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.0.get().and_utc().into()
+    }
+}
+
+// Format number with leading zero
+fn format_number(value: i32, format: &str) -> String {
+    if format == "00" && value < 10 {
+        format!("0{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Format time from minutes since midnight
+pub fn format_time(minutes: i32) -> String {
+    // Sun times may fall on the previous or next day
+    let minutes = minutes.rem_euclid(MINUTES_PER_DAY);
+    format!("{}:{}",
+        format_number(minutes / 60, "00"),
+        format_number(minutes % 60, "00"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn fixed_clock() {
+        let clock = FixedClock::new(NaiveDate::from_ymd_opt(2026, 1, 15).unwrap().and_hms_opt(5, 30, 45).unwrap());
+        assert_eq!(clock.local_time(None), 5 * 60 + 30);
+        assert_eq!(clock.local_time_precise(Some(chrono_tz::Europe::Paris)), 5.0 * 60.0 + 30.75);
+        assert_eq!(clock.month_day(None), (1, 15));
+        clock.set(NaiveDate::from_ymd_opt(2026, 7, 4).unwrap().and_hms_opt(23, 59, 0).unwrap());
+        assert_eq!(clock.minute(None), 59);
+        assert_eq!(clock.month_day(None), (7, 4));
+    }
+
+    #[test]
+    fn mired_known_values() {
+        assert_eq!(mired_to_kelvin(250.0), 4000);
+        assert_eq!(mired_to_kelvin(153.85), 6500);
+        assert_eq!(kelvin_to_mired(5000), 200.0);
+    }
+
+    #[test]
+    fn kelvin_mired_round_trip() {
+        for kelvin in (TEMPERATURE_ZERO..=25000).step_by(7) {
+            assert_eq!(mired_to_kelvin(kelvin_to_mired(kelvin)), kelvin);
+        }
+    }
+
+    // Whole mireds are rounded to the nearest Kelvin: converting back is off
+    // by less than one Kelvin's worth of mireds at that temperature
+    #[test]
+    fn mired_kelvin_round_trip() {
+        for mired in 40..=1428 {
+            let kelvin = mired_to_kelvin(mired as f64);
+            let back = kelvin_to_mired(kelvin);
+            let tolerance = kelvin_to_mired(kelvin) - kelvin_to_mired(kelvin + 1);
+            assert!((back - mired as f64).abs() <= tolerance, "{} mired -> {}K -> {}", mired, kelvin, back);
+        }
+    }
+
+    #[test]
+    fn ease_endpoints_and_midpoint() {
+        for shape in [CurveShape::Linear, CurveShape::Cosine] {
+            assert_eq!(ease(0.0, shape), 0.0);
+            assert!((ease(0.5, shape) - 0.5).abs() < 1e-12);
+            assert_eq!(ease(1.0, shape), 1.0);
+            // Positions outside the ramp are clamped
+            assert_eq!(ease(-0.5, shape), 0.0);
+            assert_eq!(ease(1.5, shape), 1.0);
+        }
+    }
+
+    #[test]
+    fn ease_is_monotonic() {
+        for shape in [CurveShape::Linear, CurveShape::Cosine] {
+            let mut previous = ease(0.0, shape);
+            for step in 1..=1000 {
+                let value = ease(step as f64 / 1000.0, shape);
+                assert!(value >= previous, "{:?} at step {}", shape, step);
+                previous = value;
+            }
+        }
+    }
+
+    // The cosine ramp leaves night and reaches day with zero slope: a small
+    // step at either end moves it far less than the step itself
+    #[test]
+    fn cosine_ease_is_flat_at_both_ends() {
+        let h = 1e-4;
+        assert!(ease(h, CurveShape::Cosine) < h * 1e-3);
+        assert!(1.0 - ease(1.0 - h, CurveShape::Cosine) < h * 1e-3);
+        assert_eq!(ease(h, CurveShape::Linear), h);
+    }
+
+    // Sydney on a UTC clock: daylight from 19:12 the previous day to 08:09
+    #[test]
+    fn daylight_across_midnight() {
+        let curve = CurveSettings {
+            day_temp: 6500,
+            night_temp: 4500,
+            transition_minutes: Some(60),
+            dawn_minutes: None,
+            dusk_minutes: None,
+            shape: CurveShape::Linear,
+            invert: false,
+            stops: Vec::new(),
+        };
+        let (sunrise, sunset) = (-288, 489);
+        assert_eq!(target_kelvin(0.0, sunrise, sunset, &curve), 6500);
+        assert_eq!(target_kelvin(23.0 * 60.0, sunrise, sunset, &curve), 6500);
+        assert_eq!(target_kelvin(12.0 * 60.0, sunrise, sunset, &curve), 4500);
+        assert_eq!(target_kelvin(19.0 * 60.0 + 42.0, sunrise, sunset, &curve), 5500);
+    }
+
+    #[test]
+    fn clock_times() {
+        assert_eq!(parse_clock_time("21:30"), Ok(21 * 60 + 30));
+        assert_eq!(parse_clock_time("7:05"), Ok(7 * 60 + 5));
+        assert_eq!(parse_clock_time("00:00"), Ok(0));
+        for bad in ["24:00", "12:60", "12", "12:3x", "-1:00", "+1:00", "012:00"] {
+            assert!(parse_clock_time(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn day_times_are_clamped() {
+        // Summer evening: sunset 21:40 moved back to 21:00, morning untouched
+        assert_eq!(clamp_day_times(330, 1300, Some(420), Some(1260)), (420, 1260));
+        // Limits outside daylight change nothing
+        assert_eq!(clamp_day_times(480, 1020, Some(420), Some(1260)), (480, 1020));
+        // Polar day becomes 07:00 to 22:00
+        assert_eq!(clamp_day_times(0, MINUTES_PER_DAY, Some(420), Some(1320)), (420, 1320));
+        // Polar night stays night
+        let (sunrise, sunset) = clamp_day_times(720, 720, Some(420), Some(1320));
+        assert_eq!(polar_day(sunrise, sunset), Some(false));
+        // Daylight across midnight (Sydney on a UTC clock): 07:00 is before sunset
+        assert_eq!(clamp_day_times(-288, 489, None, Some(420)), (-288, 420));
+        assert_eq!(clamp_day_times(-288, 489, Some(23 * 60), None), (-60, 489));
+    }
+
+    #[test]
+    fn dawn_and_dusk_durations() {
+        let curve = CurveSettings {
+            day_temp: 6500,
+            night_temp: 4500,
+            transition_minutes: Some(60),
+            dawn_minutes: Some(120),
+            dusk_minutes: Some(0),
+            shape: CurveShape::Linear,
+            invert: false,
+            stops: Vec::new(),
+        };
+        let (sunrise, sunset) = (7 * 60, 19 * 60);
+        assert_eq!(target_kelvin((7 * 60 + 60) as f64, sunrise, sunset, &curve), 5500);
+        assert_eq!(target_kelvin((9 * 60) as f64, sunrise, sunset, &curve), 6500);
+        assert_eq!(target_kelvin((19 * 60 - 1) as f64, sunrise, sunset, &curve), 6500);
+        assert_eq!(target_kelvin((19 * 60) as f64, sunrise, sunset, &curve), 4500);
+
+        // Unset, a side takes transition_minutes
+        let curve = CurveSettings { dawn_minutes: None, dusk_minutes: Some(30), ..curve };
+        assert_eq!(target_kelvin((7 * 60 + 30) as f64, sunrise, sunset, &curve), 5500);
+        assert_eq!(target_kelvin((19 * 60 - 15) as f64, sunrise, sunset, &curve), 5500);
+    }
+}
@@ -0,0 +1,125 @@
+// systemd user unit for starting colorwarm with the session:
+// `colorwarm install-service [OPTIONS]` and `colorwarm uninstall-service`
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const UNIT_NAME: &str = "colorwarm.service";
+const TARGET: &str = "graphical-session.target";
+const SYSTEMD_RUNNING: &str = "/run/systemd/system"; // Present when systemd is the init system
+
+/// Unit file location: $XDG_CONFIG_HOME/systemd/user/colorwarm.service,
+/// falling back to $HOME/.config/systemd/user/colorwarm.service
+pub fn unit_path() -> Option<PathBuf> {
+    Some(unit_dir()?.join(UNIT_NAME))
+}
+
+fn unit_dir() -> Option<PathBuf> {
+    let base = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(base.join("systemd").join("user"))
+}
+
+/// True when the machine runs systemd, so a user unit can be started
+pub fn systemd_available() -> bool {
+    Path::new(SYSTEMD_RUNNING).is_dir()
+}
+
+/// Unit running `exe` with `options` in the foreground, for as long as the
+/// graphical session
+pub fn unit_file(exe: &Path, options: &[String]) -> String {
+    let command: Vec<String> = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(options.iter().cloned())
+        .map(|word| quote(&word))
+        .collect();
+    // Readiness and watchdog only when built to send them
+    let (service_type, watchdog) = if cfg!(feature = "systemd") {
+        ("notify", "WatchdogSec=60\n")
+    } else {
+        ("simple", "")
+    };
+    format!(
+        "[Unit]\n\
+         Description=ColorWarm screen color temperature\n\
+         PartOf={target}\n\
+         After={target}\n\
+         \n\
+         [Service]\n\
+         Type={service_type}\n\
+         ExecStart={command}\n\
+         {watchdog}\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy={target}\n",
+        target = TARGET,
+        service_type = service_type,
+        command = command.join(" "),
+        watchdog = watchdog,
+    )
+}
+
+/// Write the unit for the running executable with `options`, replacing a
+/// previous one. Returns its path.
+pub fn install(options: &[String]) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = unit_path().ok_or("cannot locate the systemd user directory (HOME is not set)")?;
+    let exe = env::current_exe()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, unit_file(&exe, options))?;
+    Ok(path)
+}
+
+/// Remove the unit and its enablement link. Returns false when there was
+/// no unit.
+pub fn uninstall() -> io::Result<bool> {
+    let dir = unit_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))?;
+    let link = dir.join(format!("{}.wants", TARGET)).join(UNIT_NAME);
+    match fs::remove_file(&link) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    match fs::remove_file(dir.join(UNIT_NAME)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+// One word of ExecStart: double quoted when needed, with the specifier
+// (%) and variable ($) characters of systemd doubled
+fn quote(word: &str) -> String {
+    let word = word.replace('%', "%%").replace('$', "$$");
+    let plain = !word.is_empty()
+        && !word.chars().any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';'));
+    if plain {
+        return word;
+    }
+    format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_start_quoting() {
+        assert_eq!(quote("--lat"), "--lat");
+        assert_eq!(quote("50.85"), "50.85");
+        assert_eq!(quote("buenos aires"), "\"buenos aires\"");
+        assert_eq!(quote("50%"), "50%%");
+        assert_eq!(quote("$HOME/a\"b"), "\"$$HOME/a\\\"b\"");
+        assert_eq!(quote(""), "\"\"");
+        assert_eq!(quote(";"), "\";\"");
+
+        let options = ["--city".to_string(), "new york".to_string(), "--transition".to_string()];
+        let unit = unit_file(Path::new("/usr/local/bin/colorwarm"), &options);
+        assert!(unit.contains("\nExecStart=/usr/local/bin/colorwarm --city \"new york\" --transition\n"), "{}", unit);
+        assert!(unit.contains("\nWantedBy=graphical-session.target\n"));
+    }
+}
@@ -0,0 +1,467 @@
+// Sunrise/sunset times: astronomical computation or monthly table
+
+use std::str::FromStr;
+
+use chrono::{Datelike, Local, NaiveDate, Offset, TimeZone};
+use chrono_tz::{OffsetComponents, Tz};
+use serde::Deserialize;
+
+use crate::location::get_longitude_offset;
+use crate::suntimes::SunTimesFile;
+use crate::xsct::double_trim;
+
+const SUN_ZENITH: f64 = 90.833; // Geometric horizon + refraction + solar radius
+
+// Daylight saving time in Brussels on the 15th of each month, where the
+// table was taken. It is removed so the table holds standard time, and the
+// DST of the actual timezone and date is added back.
+const TABLE_DST: [i32; 12] = [0, 0, 0, 60, 60, 60, 60, 60, 60, 60, 0, 0];
+
+/// Sun position that counts as sunrise and sunset: the horizon crossing, or
+/// the start of morning and end of evening twilight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Twilight {
+    #[default]
+    None,
+    /// Sun 6 degrees below the horizon
+    Civil,
+    /// Sun 12 degrees below the horizon
+    Nautical,
+    /// Sun 18 degrees below the horizon
+    Astronomical,
+}
+
+impl Twilight {
+    /// Zenith angle of the sun at that crossing, in degrees
+    pub fn zenith(self) -> f64 {
+        match self {
+            Twilight::None => SUN_ZENITH,
+            Twilight::Civil => 96.0,
+            Twilight::Nautical => 102.0,
+            Twilight::Astronomical => 108.0,
+        }
+    }
+}
+
+impl FromStr for Twilight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Twilight::None),
+            "civil" => Ok(Twilight::Civil),
+            "nautical" => Ok(Twilight::Nautical),
+            "astronomical" => Ok(Twilight::Astronomical),
+            _ => Err(format!("unknown twilight '{}' (expected none, civil, nautical or astronomical)", s)),
+        }
+    }
+}
+
+/// Sunrise/sunset times for the 15th of each month (in minutes since midnight - LOCAL STANDARD TIME)
+/// When coordinates are known, the times are computed astronomically instead
+#[derive(Debug)]
+pub struct MonthlyTimes {
+    sunrise: [i32; 12],  // 0-11 for Jan-Dec (LOCAL STANDARD TIME)
+    sunset: [i32; 12],   // 0-11 for Jan-Dec (LOCAL STANDARD TIME)
+    timezone: Option<Tz>, // Clock of the times, the system one when None
+    coordinates: Option<(f64, f64)>, // (latitude, longitude) in degrees
+    offset_minutes: i32, // Added to both times, positive is later
+    twilight: Twilight, // Crossing used as sunrise/sunset with coordinates
+    sun_times: Option<SunTimesFile>, // Times given by date, before any computation
+}
+
+impl MonthlyTimes {
+    /// Times of the monthly table, shifted by an approximate longitude
+    /// offset, in the clock of `timezone` (an IANA name, else the system one)
+    pub fn new_for_timezone(timezone: &str) -> Self {
+        // Adjust times slightly based on timezone longitude
+        let longitude_offset = get_longitude_offset(timezone);
+        
+        let mut times = MonthlyTimes {
+            // January - adjusted for timezone
+            sunrise: [
+                8 * 60 + 40 + longitude_offset,    // 8:40
+                7 * 60 + 57 + longitude_offset,    // 7:57 (February)
+                6 * 60 + 57 + longitude_offset,    // 6:57 (March)
+                6 * 60 + 49 + longitude_offset,    // 6:49 (April)
+                5 * 60 + 54 + longitude_offset,    // 5:54 (May)
+                5 * 60 + 29 + longitude_offset,    // 5:29 (June)
+                5 * 60 + 47 + longitude_offset,    // 5:47 (July)
+                6 * 60 + 31 + longitude_offset,    // 6:31 (August)
+                7 * 60 + 10 + longitude_offset,    // 7:10 (September)
+                8 * 60 + 6 + longitude_offset,     // 8:06 (October)
+                7 * 60 + 59 + longitude_offset,    // 7:59 (November)
+                8 * 60 + 39 + longitude_offset,    // 8:39 (December)
+            ],
+            sunset: [
+                17 * 60 + 5 + longitude_offset,    // 17:05
+                17 * 60 + 56 + longitude_offset,   // 17:56
+                18 * 60 + 46 + longitude_offset,   // 18:46
+                20 * 60 + 37 + longitude_offset,   // 20:37
+                21 * 60 + 24 + longitude_offset,   // 21:24
+                21 * 60 + 56 + longitude_offset,   // 21:56
+                21 * 60 + 48 + longitude_offset,   // 21:48
+                21 * 60 + 1 + longitude_offset,    // 21:01
+                19 * 60 + 55 + longitude_offset,  // 19:55
+                18 * 60 + 49 + longitude_offset,  // 18:49
+                16 * 60 + 54 + longitude_offset,  // 16:54
+                16 * 60 + 36 + longitude_offset,  // 16:36
+            ],
+            timezone: timezone.parse().ok(),
+            coordinates: None,
+            offset_minutes: 0,
+            twilight: Twilight::None,
+            sun_times: None,
+        };
+        for (month, dst) in TABLE_DST.iter().enumerate() {
+            times.sunrise[month] -= dst;
+            times.sunset[month] -= dst;
+        }
+        times
+    }
+
+    /// Use real solar computation for the given coordinates, the times
+    /// given in the clock of `timezone`
+    pub fn new_for_coordinates(timezone: &str, latitude: f64, longitude: f64) -> Self {
+        MonthlyTimes {
+            coordinates: Some((latitude, longitude)),
+            ..MonthlyTimes::new_for_timezone(timezone)
+        }
+    }
+
+    /// Switch to the solar computation for new coordinates, e.g. a new
+    /// position from gpsd
+    pub fn set_coordinates(&mut self, latitude: f64, longitude: f64) {
+        self.coordinates = Some((latitude, longitude));
+    }
+
+    /// Shift sunrise and sunset by `minutes`: positive delays the schedule,
+    /// negative advances it
+    pub fn with_offset(self, minutes: i32) -> Self {
+        MonthlyTimes { offset_minutes: minutes, ..self }
+    }
+
+    /// Take the start and end of twilight as sunrise and sunset. Only the
+    /// solar computation can do this: the monthly table stays at the horizon.
+    pub fn with_twilight(self, twilight: Twilight) -> Self {
+        MonthlyTimes { twilight, ..self }
+    }
+
+    /// Take sunrise and sunset from a file for the dates it lists, the
+    /// offset still applying
+    pub fn with_sun_times(self, file: Option<SunTimesFile>) -> Self {
+        MonthlyTimes { sun_times: file, ..self }
+    }
+
+    /// True when sunrise and sunset are computed from coordinates
+    pub fn has_coordinates(&self) -> bool {
+        self.coordinates.is_some()
+    }
+}
+
+/// Compute local sunrise/sunset (minutes since midnight) with the NOAA solar position algorithm.
+/// In polar day the sunset comes 24h after the sunrise, in polar night both
+/// are equal (see `schedule::polar_day`).
+pub fn compute_sun_times(latitude: f64, longitude: f64, date: NaiveDate) -> (i32, i32) {
+    compute_sun_times_at(latitude, longitude, date, SUN_ZENITH)
+}
+
+/// Like `compute_sun_times` for another sun position, given as its zenith
+/// angle in degrees (see `Twilight::zenith`): when the sun rises above it in
+/// the morning and sinks below it in the evening
+pub fn compute_sun_times_at(latitude: f64, longitude: f64, date: NaiveDate, zenith: f64) -> (i32, i32) {
+    solar_times(latitude, longitude, date, zenith, utc_offset_minutes(None, date))
+}
+
+/// Like `compute_sun_times_at` with the times in the clock of `timezone`
+/// instead of the system one, e.g. for a remote location
+pub fn compute_sun_times_in(timezone: Tz, latitude: f64, longitude: f64, date: NaiveDate, zenith: f64) -> (i32, i32) {
+    solar_times(latitude, longitude, date, zenith, utc_offset_minutes(Some(timezone), date))
+}
+
+// UTC offset at noon on that date in `timezone` (or the system one), in
+// minutes, daylight saving time included
+fn utc_offset_minutes(timezone: Option<Tz>, date: NaiveDate) -> i32 {
+    let Some(noon) = date.and_hms_opt(12, 0, 0) else {
+        return 0;
+    };
+    let offset = match timezone {
+        Some(timezone) => timezone.from_local_datetime(&noon).earliest().map(|dt| dt.offset().fix()),
+        None => Local.from_local_datetime(&noon).earliest().map(|dt| dt.offset().fix()),
+    };
+    offset.map_or(0, |offset| offset.local_minus_utc() / 60)
+}
+
+fn solar_times(latitude: f64, longitude: f64, date: NaiveDate, zenith: f64, utc_offset: i32) -> (i32, i32) {
+    // Fractional year (radians), taken at solar noon
+    let gamma = 2.0 * std::f64::consts::PI / 365.0 * (date.ordinal0() as f64);
+
+    // Equation of time (minutes)
+    let eqtime = 229.18 * (0.000075 + 0.001868 * gamma.cos() - 0.032077 * gamma.sin()
+        - 0.014615 * (2.0 * gamma).cos() - 0.040849 * (2.0 * gamma).sin());
+
+    // Solar declination (radians)
+    let decl = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos() + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos() + 0.00148 * (3.0 * gamma).sin();
+
+    // Hour angle of sunrise/sunset. Clamped so polar day gives a 24h day
+    // and polar night a zero-length day.
+    let lat = latitude.to_radians();
+    let cos_ha = zenith.to_radians().cos() / (lat.cos() * decl.cos()) - lat.tan() * decl.tan();
+    let ha = double_trim(cos_ha, -1.0, 1.0).acos().to_degrees();
+
+    let utc_offset = utc_offset as f64;
+    let sunrise = 720.0 - 4.0 * (longitude + ha) - eqtime + utc_offset;
+    let sunset = 720.0 - 4.0 * (longitude - ha) - eqtime + utc_offset;
+
+    (sunrise.round() as i32, sunset.round() as i32)
+}
+
+/// Get smoothed sunrise/sunset times (using your original algorithm) for a
+/// date, shifted by the offset of `MonthlyTimes::with_offset`
+pub fn get_smoothed_day_times(monthly_times: &MonthlyTimes, date: NaiveDate) -> (i32, i32) {
+    let (sunrise, sunset) = sun_day_times(monthly_times, date);
+    (sunrise + monthly_times.offset_minutes, sunset + monthly_times.offset_minutes)
+}
+
+// Sunrise/sunset before the user offset. Both are recomputed for each date,
+// so a daylight saving time change applies on its day.
+fn sun_day_times(monthly_times: &MonthlyTimes, date: NaiveDate) -> (i32, i32) {
+    let from_file = monthly_times.sun_times.as_ref().and_then(|file| file.day_times(date));
+    if let Some(times) = from_file {
+        return times;
+    }
+    if let Some((latitude, longitude)) = monthly_times.coordinates {
+        let utc_offset = utc_offset_minutes(monthly_times.timezone, date);
+        return solar_times(latitude, longitude, date, monthly_times.twilight.zenith(), utc_offset);
+    }
+
+    let (sunrise, sunset) = table_day_times(monthly_times, date);
+    let dst = monthly_times.timezone.map_or(0, |timezone| dst_minutes(timezone, date));
+    (sunrise + dst, sunset + dst)
+}
+
+// Daylight saving time in effect at noon on that date, in minutes
+fn dst_minutes(timezone: Tz, date: NaiveDate) -> i32 {
+    date.and_hms_opt(12, 0, 0)
+        .and_then(|noon| timezone.from_local_datetime(&noon).earliest())
+        .map_or(0, |dt| dt.offset().dst_offset().num_minutes() as i32)
+}
+
+// Table times interpolated for the date, in standard time
+fn table_day_times(monthly_times: &MonthlyTimes, date: NaiveDate) -> (i32, i32) {
+    let (year, month, day) = (date.year(), date.month() as usize, date.day() as i32);
+    // Month is 1-12, convert to 0-11 for array indexing
+    let month_index = month - 1;
+    
+    // Interpolate between the 15th of two consecutive months, over the
+    // real length of that interval (28 to 31 days)
+    let (month1, month2, days_since_sample, interval) = if day <= 15 {
+        // First half of month: days since the previous month's 15th, so
+        // the ratio reaches 1 (this month's sample) on the 15th itself
+        let month1 = if month_index == 0 { 11 } else { month_index - 1 };
+        let month2 = month_index;
+        let interval = days_in_month(year, month1 + 1);
+        (month1, month2, day + interval - 15, interval)
+    } else {
+        // Second half of month
+        let month1 = month_index;
+        let month2 = (month_index + 1) % 12;
+        (month1, month2, day - 15, days_in_month(year, month))
+    };
+    
+    // Calculate interpolation ratio
+    let ratio = (days_since_sample as f32 / interval as f32).clamp(0.0, 1.0);
+    
+    // Linear interpolation
+    let sunrise = (monthly_times.sunrise[month1] as f32 +
+                  (monthly_times.sunrise[month2] as f32 - monthly_times.sunrise[month1] as f32) * ratio)
+                  .round() as i32;
+    
+    let sunset = (monthly_times.sunset[month1] as f32 +
+                  (monthly_times.sunset[month2] as f32 - monthly_times.sunset[month1] as f32) * ratio)
+                  .round() as i32;
+    
+    (sunrise, sunset)
+}
+
+// Number of days in a month (1-12), February depending on leap years
+fn days_in_month(year: i32, month: usize) -> i32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month as u32 + 1) };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|first| first.pred_opt())
+        .map_or(30, |last| last.day() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::format_time;
+
+    const TIMEZONES: [&str; 3] = ["Europe/Brussels", "America/New_York", "Asia/Tokyo"];
+    const DAYS_IN_MONTH: [i32; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+    fn date(year: i32, month: usize, day: i32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+    }
+
+    // Largest daily change allowed between the two table samples around a date
+    fn max_step(times: &MonthlyTimes, month1: usize, month2: usize) -> i32 {
+        let sunrise = (times.sunrise[month2] - times.sunrise[month1]).abs();
+        let sunset = (times.sunset[month2] - times.sunset[month1]).abs();
+        (sunrise.max(sunset) as f32 / 28.0).ceil() as i32 + 1
+    }
+
+    fn assert_close(before: (i32, i32), after: (i32, i32), limit: i32, context: &str) {
+        assert!(
+            (after.0 - before.0).abs() <= limit && (after.1 - before.1).abs() <= limit,
+            "{}: jump from {:?} to {:?} (limit {} min)",
+            context, before, after, limit
+        );
+    }
+
+    #[test]
+    fn continuous_across_mid_month() {
+        for tz in TIMEZONES {
+            let times = MonthlyTimes::new_for_timezone(tz);
+            for month in 1..=12 {
+                let limit = max_step(&times, month - 1, month % 12);
+                let before = get_smoothed_day_times(&times, date(2026, month, 15));
+                let after = get_smoothed_day_times(&times, date(2026, month, 16));
+                assert_close(before, after, limit, &format!("{} {}/15 -> {}/16", tz, month, month));
+            }
+        }
+    }
+
+    #[test]
+    fn continuous_across_year_end() {
+        for tz in TIMEZONES {
+            let times = MonthlyTimes::new_for_timezone(tz);
+            let limit = max_step(&times, 11, 0);
+            let before = get_smoothed_day_times(&times, date(2026, 12, 31));
+            let after = get_smoothed_day_times(&times, date(2027, 1, 1));
+            assert_close(before, after, limit, &format!("{} 12/31 -> 1/1", tz));
+        }
+    }
+
+    // Between two table samples (15th to 15th) the times only move one way,
+    // leaving aside the daylight saving time step
+    #[test]
+    fn monotonic_between_samples() {
+        for tz in TIMEZONES {
+            let times = MonthlyTimes::new_for_timezone(tz);
+            for month in 1..=12 {
+                let (next_year, next) = if month == 12 { (2027, 1) } else { (2026, month + 1) };
+                let days: Vec<NaiveDate> = (15..=DAYS_IN_MONTH[month - 1])
+                    .map(|day| date(2026, month, day))
+                    .chain((1..=15).map(|day| date(next_year, next, day)))
+                    .collect();
+                let values: Vec<(i32, i32)> = days.iter().map(|&day| table_day_times(&times, day)).collect();
+
+                for (index, pick) in [|v: &(i32, i32)| v.0, |v: &(i32, i32)| v.1].iter().enumerate() {
+                    let series: Vec<i32> = values.iter().map(pick).collect();
+                    let rising = series.windows(2).all(|w| w[0] <= w[1]);
+                    let falling = series.windows(2).all(|w| w[0] >= w[1]);
+                    assert!(
+                        rising || falling,
+                        "{} {} from {}/15: not monotonic: {:?}",
+                        tz, if index == 0 { "sunrise" } else { "sunset" }, month, series
+                    );
+                }
+            }
+        }
+    }
+
+    // The table holds the 15th of each month: no interpolation on that day
+    #[test]
+    fn fifteenth_equals_table() {
+        for tz in TIMEZONES {
+            let times = MonthlyTimes::new_for_timezone(tz);
+            for month in 1..=12 {
+                assert_eq!(
+                    table_day_times(&times, date(2026, month, 15)),
+                    (times.sunrise[month - 1], times.sunset[month - 1]),
+                    "{} {}/15", tz, month
+                );
+            }
+        }
+    }
+
+    // The 1st is 14 days before this month's 15th and 16 to 17 days after the
+    // previous one, so it must be weighted towards this month's sample
+    #[test]
+    fn first_of_month_closer_to_current_sample() {
+        for tz in TIMEZONES {
+            let times = MonthlyTimes::new_for_timezone(tz);
+            for month in 1..=12 {
+                let previous = (month + 10) % 12;
+                let (sunrise, sunset) = table_day_times(&times, date(2026, month, 1));
+                for (value, table) in [(sunrise, &times.sunrise), (sunset, &times.sunset)] {
+                    assert!(
+                        (value - table[month - 1]).abs() <= (value - table[previous]).abs(),
+                        "{} {}/1: {} is closer to {} than to {}",
+                        tz, month, value, table[previous], table[month - 1]
+                    );
+                }
+            }
+        }
+    }
+
+    // The year is the one of the date asked for: Feb 29 exists in 2028 and
+    // gets the summer time of Sydney like the days around it
+    #[test]
+    fn leap_day_keeps_daylight_saving_time() {
+        let times = MonthlyTimes::new_for_timezone("Australia/Sydney");
+        let (sunrise, sunset) = table_day_times(&times, date(2028, 2, 29));
+        assert_eq!(get_smoothed_day_times(&times, date(2028, 2, 29)), (sunrise + 60, sunset + 60));
+        assert_eq!(days_in_month(2028, 2), 29);
+    }
+
+    // The DST change moves the times by an hour from one day to the next
+    // instead of being spread over the month around it
+    #[test]
+    fn dst_applies_on_its_day() {
+        let brussels: Tz = "Europe/Brussels".parse().unwrap();
+        let date = |month, day| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+        assert_eq!(dst_minutes(brussels, date(3, 28)), 0);
+        assert_eq!(dst_minutes(brussels, date(3, 29)), 60);
+        assert_eq!(dst_minutes(brussels, date(10, 24)), 60);
+        assert_eq!(dst_minutes(brussels, date(10, 25)), 0);
+        assert_eq!(dst_minutes("Asia/Tokyo".parse().unwrap(), date(7, 1)), 0);
+    }
+
+    // Times in the clock of the given zone, whatever the system timezone:
+    // New York on summer time, Kathmandu at UTC+5:45
+    #[test]
+    fn times_in_a_remote_timezone() {
+        let date = NaiveDate::from_ymd_opt(2026, 6, 21).unwrap();
+        let new_york = compute_sun_times_in("America/New_York".parse().unwrap(), 40.71, -74.01, date, SUN_ZENITH);
+        assert!((5 * 60 + 20..=5 * 60 + 30).contains(&new_york.0), "New York sunrise {}", format_time(new_york.0));
+        assert!((20 * 60 + 25..=20 * 60 + 35).contains(&new_york.1), "New York sunset {}", format_time(new_york.1));
+        let kathmandu = compute_sun_times_in("Asia/Kathmandu".parse().unwrap(), 27.72, 85.32, date, SUN_ZENITH);
+        assert!((5 * 60 + 4..=5 * 60 + 14).contains(&kathmandu.0), "Kathmandu sunrise {}", format_time(kathmandu.0));
+
+        let times = MonthlyTimes::new_for_coordinates("Asia/Kathmandu", 27.72, 85.32);
+        assert_eq!(
+            get_smoothed_day_times(&times, date),
+            compute_sun_times_in("Asia/Kathmandu".parse().unwrap(), 27.72, 85.32, date, SUN_ZENITH)
+        );
+    }
+
+    // Twilight starts before sunrise and ends after sunset, deeper ones
+    // further out (civil twilight lasts about 35 minutes in Brussels in October)
+    #[test]
+    fn twilight_brackets_the_day() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let at = |twilight: Twilight| compute_sun_times_at(50.85, 4.35, date, twilight.zenith());
+        let (sunrise, sunset) = at(Twilight::None);
+        assert_eq!((sunrise, sunset), compute_sun_times(50.85, 4.35, date));
+        let (civil_dawn, civil_dusk) = at(Twilight::Civil);
+        assert!((25..=45).contains(&(sunrise - civil_dawn)), "civil dawn {} before sunrise {}", civil_dawn, sunrise);
+        assert!((25..=45).contains(&(civil_dusk - sunset)), "civil dusk {} after sunset {}", civil_dusk, sunset);
+        let (nautical_dawn, nautical_dusk) = at(Twilight::Nautical);
+        assert!(nautical_dawn < civil_dawn && nautical_dusk > civil_dusk);
+    }
+}
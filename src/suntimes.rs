@@ -0,0 +1,102 @@
+// Sunrise/sunset times read from a JSON file (--suntimes-file), e.g. fetched
+// daily from an online service, in place of the built-in computation
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::NaiveDate;
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::schedule::parse_clock_time;
+
+// One date of the file (other keys are ignored)
+#[derive(Deserialize)]
+struct Day {
+    sunrise: String,
+    sunset: String,
+}
+
+/// Sunrise and sunset by date from a file, read again whenever it changes
+#[derive(Debug)]
+pub struct SunTimesFile {
+    path: PathBuf,
+    modified: Cell<Option<SystemTime>>,
+    days: RefCell<HashMap<NaiveDate, (i32, i32)>>,
+}
+
+impl SunTimesFile {
+    /// Read a file mapping dates to local times:
+    /// {"2026-06-21": {"sunrise": "05:29", "sunset": "22:00"}, ...}
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = SunTimesFile {
+            path: path.to_path_buf(),
+            modified: Cell::new(modified(path)),
+            days: RefCell::new(read(path)?),
+        };
+        Ok(file)
+    }
+
+    /// Sunrise and sunset (minutes since midnight) of a date, if the file
+    /// has it. A file that became invalid is reported once and its previous
+    /// content kept.
+    pub fn day_times(&self, date: NaiveDate) -> Option<(i32, i32)> {
+        let modified = modified(&self.path);
+        if modified != self.modified.replace(modified) {
+            match read(&self.path) {
+                Ok(days) => {
+                    debug!("Sun times reloaded from {}", self.path.display());
+                    *self.days.borrow_mut() = days;
+                }
+                Err(e) => warn!("{}, keeping the previous sun times", e),
+            }
+        }
+        self.days.borrow().get(&date).copied()
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn read(path: &Path) -> Result<HashMap<NaiveDate, (i32, i32)>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    parse(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse(content: &str) -> Result<HashMap<NaiveDate, (i32, i32)>, String> {
+    let days: HashMap<String, Day> = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let mut times = HashMap::new();
+    for (date, day) in days {
+        let parsed = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|_| format!("invalid date '{}' (expected YYYY-MM-DD)", date))?;
+        let sunrise = parse_clock_time(&day.sunrise).map_err(|e| format!("{}: {}", date, e))?;
+        let sunset = parse_clock_time(&day.sunset).map_err(|e| format!("{}: {}", date, e))?;
+        if sunset <= sunrise {
+            return Err(format!("{}: sunset {} is not after sunrise {}", date, day.sunset, day.sunrise));
+        }
+        times.insert(parsed, (sunrise, sunset));
+    }
+    Ok(times)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_and_errors() {
+        let times = parse(r#"{"2026-06-21": {"sunrise": "05:29", "sunset": "22:00", "day_length": 59460}}"#).unwrap();
+        let solstice = NaiveDate::from_ymd_opt(2026, 6, 21).unwrap();
+        assert_eq!(times.get(&solstice), Some(&(5 * 60 + 29, 22 * 60)));
+        assert_eq!(times.len(), 1);
+
+        assert!(parse(r#"{"21/06/2026": {"sunrise": "05:29", "sunset": "22:00"}}"#).is_err());
+        assert!(parse(r#"{"2026-06-21": {"sunrise": "5h29", "sunset": "22:00"}}"#).is_err());
+        assert!(parse(r#"{"2026-06-21": {"sunrise": "22:00", "sunset": "05:29"}}"#).is_err());
+        assert!(parse(r#"{"2026-06-21": {"sunrise": "05:29"}}"#).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+// systemd service notifications (Type=notify units and WatchdogSec=)
+//
+// Only compiled in with the "systemd" feature; without it every function is
+// a no-op, so the main loop does not need to know.
+
+#[cfg(feature = "systemd")]
+pub use notify::{notify_ready, watchdog_period, watchdog_ping};
+
+#[cfg(not(feature = "systemd"))]
+pub use disabled::{notify_ready, watchdog_period, watchdog_ping};
+
+#[cfg(feature = "systemd")]
+mod notify {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use sd_notify::NotifyState;
+
+    static READY_SENT: AtomicBool = AtomicBool::new(false);
+
+    /// Tell systemd the service is up. Only the first call sends anything.
+    pub fn notify_ready() {
+        if !READY_SENT.swap(true, Ordering::Relaxed) {
+            send(&[NotifyState::Ready]);
+        }
+    }
+
+    /// Keep-alive for the systemd watchdog
+    pub fn watchdog_ping() {
+        send(&[NotifyState::Watchdog]);
+    }
+
+    /// How often to ping the watchdog: half the WATCHDOG_USEC timeout, or
+    /// None when the unit has no watchdog (or it is meant for another pid)
+    pub fn watchdog_period() -> Option<Duration> {
+        let mut usec = 0;
+        if sd_notify::watchdog_enabled(false, &mut usec) && usec > 0 {
+            Some(Duration::from_micros(usec / 2))
+        } else {
+            None
+        }
+    }
+
+    // Without NOTIFY_SOCKET (not started by systemd) this does nothing
+    fn send(state: &[NotifyState]) {
+        if let Err(e) = sd_notify::notify(false, state) {
+            log::warn!("systemd notification failed: {}", e);
+        }
+    }
+}
+
+#[cfg(not(feature = "systemd"))]
+mod disabled {
+    use std::time::Duration;
+
+    pub fn notify_ready() {}
+
+    pub fn watchdog_ping() {}
+
+    pub fn watchdog_period() -> Option<Duration> {
+        None
+    }
+}
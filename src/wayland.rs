@@ -0,0 +1,218 @@
+// Wayland backend using the wlr-gamma-control protocol (Sway, Hyprland, river...)
+//
+// The compositor restores the original gamma as soon as a gamma control object
+// is destroyed, so the connection has to stay open for the settings to persist.
+
+use std::cell::{Cell, RefCell};
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::os::fd::{AsFd, FromRawFd};
+
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_output, wl_registry};
+use wayland_client::{Connection, Dispatch, EventQueue, QueueHandle};
+use wayland_protocols_wlr::gamma_control::v1::client::{
+    zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1},
+    zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+};
+
+use crate::backend::Backend;
+use crate::xsct::{double_trim, RampCache, TempStatus, WhitePoint, GAMMA_LINEAR, TEMPERATURE_NORM};
+
+// One gamma control per output
+struct OutputGamma {
+    _output: wl_output::WlOutput,
+    control: ZwlrGammaControlV1,
+    size: usize,
+    failed: bool,
+}
+
+#[derive(Default)]
+struct GammaState {
+    outputs: Vec<OutputGamma>,
+}
+
+pub struct WaylandBackend {
+    conn: Connection,
+    queue: RefCell<EventQueue<GammaState>>,
+    state: RefCell<GammaState>,
+    current: Cell<TempStatus>,
+    exponents: Cell<(f64, f64, f64)>, // Display gamma (--gamma)
+    white_point: Cell<WhitePoint>, // Fitted curves or Planckian locus (--accurate)
+    ramps: RefCell<RampCache>, // Ramp buffers reused from one update to the next
+}
+
+impl WaylandBackend {
+    pub fn connect() -> Result<Self, Box<dyn std::error::Error>> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<GammaState>(&conn)?;
+        let qh = queue.handle();
+
+        let manager: ZwlrGammaControlManagerV1 = globals
+            .bind(&qh, 1..=1, ())
+            .map_err(|_| "compositor does not support wlr-gamma-control")?;
+
+        let mut state = GammaState::default();
+        let output_globals: Vec<(u32, u32)> = globals.contents().with_list(|list| {
+            list.iter()
+                .filter(|g| g.interface == "wl_output")
+                .map(|g| (g.name, g.version))
+                .collect()
+        });
+        for (name, version) in output_globals {
+            let output: wl_output::WlOutput =
+                globals.registry().bind(name, version.min(4), &qh, ());
+            let index = state.outputs.len();
+            let control = manager.get_gamma_control(&output, &qh, index);
+            state.outputs.push(OutputGamma {
+                _output: output,
+                control,
+                size: 0,
+                failed: false,
+            });
+        }
+
+        // Receive the gamma_size events
+        queue.roundtrip(&mut state)?;
+
+        if state.outputs.iter().all(|o| o.failed || o.size == 0) {
+            return Err("no output accepts gamma control".into());
+        }
+
+        Ok(WaylandBackend {
+            conn,
+            queue: RefCell::new(queue),
+            state: RefCell::new(state),
+            current: Cell::new(TempStatus {
+                temp: TEMPERATURE_NORM,
+                brightness: 1.0,
+            }),
+            exponents: Cell::new(GAMMA_LINEAR),
+            white_point: Cell::new(WhitePoint::Empirical),
+            ramps: RefCell::new(RampCache::new()),
+        })
+    }
+
+    fn apply(&self, temp: TempStatus) -> Result<(), Box<dyn std::error::Error>> {
+        let brightness = double_trim(temp.brightness, 0.0, 1.0);
+        let gamma = self.white_point.get().gamma(temp.temp);
+
+        let state = self.state.borrow();
+        let mut ramps = self.ramps.borrow_mut();
+        for output in state.outputs.iter().filter(|o| !o.failed && o.size > 0) {
+            let (red, green, blue) = ramps.ramps(output.size, brightness, gamma, self.exponents.get());
+
+            // The protocol expects the three ramps back to back in a file descriptor
+            let mut file = ramp_file()?;
+            for value in red.iter().chain(green.iter()).chain(blue.iter()) {
+                file.write_all(&value.to_ne_bytes())?;
+            }
+            file.flush()?;
+            file.seek(SeekFrom::Start(0))?;
+
+            output.control.set_gamma(file.as_fd());
+        }
+        drop(state);
+
+        self.conn.flush()?;
+        let mut state = self.state.borrow_mut();
+        self.queue.borrow_mut().roundtrip(&mut state)?;
+        Ok(())
+    }
+}
+
+impl Backend for WaylandBackend {
+    fn name(&self) -> &'static str {
+        "Wayland"
+    }
+
+    // The protocol cannot read gamma back, report what was last applied
+    fn get_temperature(&self) -> Result<TempStatus, Box<dyn std::error::Error>> {
+        Ok(self.current.get())
+    }
+
+    fn set_temperature(&self, temp: TempStatus) -> Result<(), Box<dyn std::error::Error>> {
+        let temp = TempStatus {
+            temp: if temp.temp == 0 { TEMPERATURE_NORM } else { temp.temp },
+            brightness: temp.brightness,
+        };
+        self.apply(temp)?;
+        self.current.set(temp);
+        Ok(())
+    }
+
+    fn set_gamma(&self, exponents: (f64, f64, f64)) {
+        self.exponents.set(exponents);
+    }
+
+    fn set_white_point(&self, white_point: WhitePoint) {
+        self.white_point.set(white_point);
+    }
+}
+
+// Anonymous memory file holding a gamma ramp
+fn ramp_file() -> std::io::Result<File> {
+    let name = CString::new("colorwarm-gamma").unwrap();
+    let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for GammaState {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, ()> for GammaState {
+    fn event(
+        _: &mut Self,
+        _: &wl_output::WlOutput,
+        _: wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for GammaState {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrGammaControlManagerV1,
+        _: zwlr_gamma_control_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, usize> for GammaState {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrGammaControlV1,
+        event: zwlr_gamma_control_v1::Event,
+        index: &usize,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let Some(output) = state.outputs.get_mut(*index) {
+            match event {
+                zwlr_gamma_control_v1::Event::GammaSize { size } => output.size = size as usize,
+                // Another client already controls this output
+                zwlr_gamma_control_v1::Event::Failed => output.failed = true,
+                _ => {}
+            }
+        }
+    }
+}
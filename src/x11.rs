@@ -0,0 +1,307 @@
+// X11 RandR backend, the integrated xsct driven by the automatic mode
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::{ConnectionExt as RandrExt, NotifyMask};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use crate::backend::{Backend, CrtcSelection};
+use crate::xsct::{
+    crtc_output_names, get_sct_for_screen_with, open_display, sct_for_screen_with_gamma, RampCache, TempLimits,
+    TempStatus, WhitePoint, GAMMA_LINEAR, TEMPERATURE_NORM, TEMPERATURE_ZERO,
+};
+
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+
+/// X11 RandR backend (the integrated xsct). The connection is opened once
+/// and kept; it is dropped on error and reopened on the next call, so the
+/// backend survives an X server restart. While the server is gone,
+/// reconnection attempts back off from 1s to a minute.
+#[derive(Default)]
+pub struct X11Backend {
+    conn: RefCell<Option<(RustConnection, usize)>>,
+    offsets: RefCell<HashMap<String, i32>>,
+    excluded: RefCell<Vec<String>>,
+    exclusions_reported: Cell<bool>, // Excluded outputs logged once
+    selection: CrtcSelection,
+    display: Option<String>, // X display name (--display), $DISPLAY if None
+    retry: Cell<Option<(Instant, Duration)>>, // Next attempt and current backoff
+    exponents: Cell<Option<(f64, f64, f64)>>, // Display gamma, linear if None
+    white_point: Cell<WhitePoint>,
+    limits: Cell<TempLimits>, // --min-temp/--max-temp, applied after the offsets
+    ramps: RefCell<RampCache>, // Ramp buffers reused from one update to the next
+}
+
+impl X11Backend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Backend that leaves the screens and CRTCs outside `selection` alone
+    pub fn with_selection(selection: CrtcSelection) -> Self {
+        X11Backend { selection, ..Self::default() }
+    }
+
+    /// Use this X display (":1", ":0.1") instead of $DISPLAY
+    pub fn on_display(self, display: Option<&str>) -> Self {
+        X11Backend { display: display.map(str::to_string), ..self }
+    }
+
+    /// Connect and check the selected screen and CRTC exist
+    pub fn check(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.with_connection(|conn, _| {
+            for screen in self.screens(conn)? {
+                self.crtc_index(conn, screen)?;
+            }
+            Ok(())
+        })
+    }
+
+    // Screens to update, checked against the server
+    fn screens(&self, conn: &RustConnection) -> Result<Range<usize>, Box<dyn std::error::Error>> {
+        let count = conn.setup().roots.len();
+        match self.selection.screen {
+            Some(screen) if screen >= count => {
+                Err(format!("invalid screen index {} (the display has {})", screen, count).into())
+            }
+            Some(screen) => Ok(screen..screen + 1),
+            None => Ok(0..count),
+        }
+    }
+
+    // `icrtc` argument of sct_for_screen: the selected CRTC, or -1 for all.
+    // sct_for_screen falls back to every CRTC when out of range, so check it.
+    fn crtc_index(&self, conn: &RustConnection, screen: usize) -> Result<i32, Box<dyn std::error::Error>> {
+        let Some(crtc) = self.selection.crtc else {
+            return Ok(-1);
+        };
+        let root = conn.setup().roots[screen].root;
+        let count = conn.randr_get_screen_resources_current(root)?.reply()?.crtcs.len();
+        if crtc >= count {
+            return Err(format!("invalid CRTC index {} (screen {} has {})", crtc, screen, count).into());
+        }
+        Ok(crtc as i32)
+    }
+
+    // CRTC to read the temperature from: the selected one, else the first
+    // lit CRTC outside the exclusions, or -1 for all when nothing is excluded
+    fn read_crtc_index(&self, conn: &RustConnection, screen: usize) -> Result<i32, Box<dyn std::error::Error>> {
+        let excluded = self.excluded.borrow();
+        if self.selection.crtc.is_some() || excluded.is_empty() {
+            return self.crtc_index(conn, screen);
+        }
+        crtc_output_names(conn, screen)?
+            .iter()
+            .position(|names| !names.is_empty() && !is_excluded(names, &excluded))
+            .map(|crtc| crtc as i32)
+            .ok_or_else(|| format!("every output of screen {} is excluded", screen).into())
+    }
+
+    // Where each excluded output is, in the debug log (once)
+    fn report_exclusions(&self, conn: &RustConnection) -> Result<(), Box<dyn std::error::Error>> {
+        if self.exclusions_reported.replace(true) {
+            return Ok(());
+        }
+        let mut found = Vec::new();
+        for screen in 0..conn.setup().roots.len() {
+            for (crtc, names) in crtc_output_names(conn, screen)?.iter().enumerate() {
+                for name in names {
+                    found.push((name.clone(), screen, crtc));
+                }
+            }
+        }
+        for name in self.excluded.borrow().iter() {
+            match found.iter().find(|(output, _, _)| output == name) {
+                Some((_, screen, crtc)) => debug!("Excluding output {} (screen {}, CRTC {})", name, screen, crtc),
+                None => debug!("Excluding output {} (not connected)", name),
+            }
+        }
+        Ok(())
+    }
+
+    // Run `f` on the cached connection, reconnecting lazily
+    fn with_connection<T>(
+        &self,
+        f: impl FnOnce(&RustConnection, usize) -> Result<T, Box<dyn std::error::Error>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let mut cached = self.conn.borrow_mut();
+        if cached.is_none() {
+            *cached = Some(self.reconnect()?);
+        }
+        let (conn, screen) = cached.as_ref().unwrap();
+
+        let result = f(conn, *screen);
+        if result.is_err() {
+            *cached = None;
+        }
+        result
+    }
+
+    // Connect, unless the previous attempt failed too recently
+    fn reconnect(&self) -> Result<(RustConnection, usize), Box<dyn std::error::Error>> {
+        if let Some((next_attempt, _)) = self.retry.get() {
+            if Instant::now() < next_attempt {
+                return Err("X server unavailable, waiting before reconnecting".into());
+            }
+        }
+
+        match connect_with_randr_events(self.display.as_deref()) {
+            Ok(connection) => {
+                if self.retry.take().is_some() {
+                    info!("Reconnected to the X server");
+                }
+                Ok(connection)
+            }
+            Err(e) => {
+                let backoff = match self.retry.get() {
+                    Some((_, backoff)) => (backoff * 2).min(RECONNECT_MAX),
+                    None => RECONNECT_MIN,
+                };
+                self.retry.set(Some((Instant::now() + backoff, backoff)));
+                Err(e)
+            }
+        }
+    }
+}
+
+// True if one of the outputs of a CRTC is excluded
+fn is_excluded(names: &[String], excluded: &[String]) -> bool {
+    names.iter().any(|name| excluded.contains(name))
+}
+
+// Open the X connection and ask for RandR screen/CRTC change events
+fn connect_with_randr_events(display: Option<&str>) -> Result<(RustConnection, usize), Box<dyn std::error::Error>> {
+    let (conn, screen) = open_display(display)?;
+    for root in &conn.setup().roots {
+        conn.randr_select_input(root.root, NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE)?;
+    }
+    conn.flush()?;
+    Ok((conn, screen))
+}
+
+impl Backend for X11Backend {
+    fn name(&self) -> &'static str {
+        "X11"
+    }
+
+    fn get_temperature(&self) -> Result<TempStatus, Box<dyn std::error::Error>> {
+        self.with_connection(|conn, default_screen| {
+            let screen = match self.selection.screen {
+                Some(_) => self.screens(conn)?.start,
+                None => default_screen,
+            };
+            get_sct_for_screen_with(conn, screen, self.read_crtc_index(conn, screen)?, self.white_point.get())
+        })
+    }
+
+    fn set_temperature(&self, temp: TempStatus) -> Result<(), Box<dyn std::error::Error>> {
+        let temp = TempStatus {
+            temp: if temp.temp == 0 { TEMPERATURE_NORM } else { temp.temp },
+            brightness: temp.brightness,
+        };
+
+        let offsets = self.offsets.borrow();
+        let excluded = self.excluded.borrow();
+        let exponents = self.exponents.get().unwrap_or(GAMMA_LINEAR);
+        let white_point = self.white_point.get();
+        let mut ramps = self.ramps.borrow_mut();
+        self.with_connection(|conn, _| {
+            if !excluded.is_empty() {
+                self.report_exclusions(conn)?;
+            }
+            for screen in self.screens(conn)? {
+                let selected = self.crtc_index(conn, screen)?;
+                if offsets.is_empty() && excluded.is_empty() {
+                    sct_for_screen_with_gamma(conn, screen, selected, temp, exponents, white_point, &mut ramps)?;
+                    continue;
+                }
+
+                // One gamma ramp per CRTC, shifted by the offset of its output,
+                // except for the excluded outputs
+                for (crtc, names) in crtc_output_names(conn, screen)?.iter().enumerate() {
+                    if (selected >= 0 && crtc as i32 != selected) || is_excluded(names, &excluded) {
+                        continue;
+                    }
+                    let offset = names.iter().find_map(|name| offsets.get(name)).copied().unwrap_or(0);
+                    let shifted = self.limits.get().clamp(TempStatus {
+                        temp: (temp.temp + offset).max(TEMPERATURE_ZERO),
+                        brightness: temp.brightness,
+                    });
+                    sct_for_screen_with_gamma(conn, screen, crtc as i32, shifted, exponents, white_point, &mut ramps)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn set_output_offsets(&self, offsets: &HashMap<String, i32>) {
+        *self.offsets.borrow_mut() = offsets.clone();
+    }
+
+    fn set_excluded_outputs(&self, names: &[String]) {
+        *self.excluded.borrow_mut() = names.to_vec();
+        self.exclusions_reported.set(false);
+    }
+
+    fn set_gamma(&self, exponents: (f64, f64, f64)) {
+        self.exponents.set(Some(exponents));
+    }
+
+    fn set_white_point(&self, white_point: WhitePoint) {
+        self.white_point.set(white_point);
+    }
+
+    fn set_temperature_limits(&self, limits: TempLimits) {
+        self.limits.set(limits);
+    }
+
+    fn event_fd(&self) -> Option<RawFd> {
+        self.conn.borrow().as_ref().map(|(conn, _)| conn.stream().as_raw_fd())
+    }
+
+    fn reconnect_in(&self) -> Option<Duration> {
+        match self.retry.get() {
+            Some((next_attempt, _)) if self.conn.borrow().is_none() => {
+                Some(next_attempt.saturating_duration_since(Instant::now()))
+            }
+            _ => None,
+        }
+    }
+
+    // Drain pending events without blocking; the CRTCs are enumerated again
+    // on every update, so a change only needs to trigger one. Without a
+    // connection, try to get one back: a new server needs the temperature.
+    fn display_changed(&self) -> bool {
+        if self.conn.borrow().is_none() {
+            return self.reconnect_in().is_some_and(|wait| wait.is_zero())
+                && self.with_connection(|_, _| Ok(())).is_ok();
+        }
+
+        let mut cached = self.conn.borrow_mut();
+        let Some((conn, _)) = cached.as_ref() else {
+            return false;
+        };
+
+        let mut changed = false;
+        loop {
+            match conn.poll_for_event() {
+                Ok(Some(Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_))) => changed = true,
+                Ok(Some(_)) => {}
+                Ok(None) => return changed,
+                Err(e) => {
+                    // Connection lost: reconnect on the next update
+                    warn!("Lost the X server connection: {}", e);
+                    *cached = None;
+                    return true;
+                }
+            }
+        }
+    }
+}
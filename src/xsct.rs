@@ -0,0 +1,913 @@
+// Integrated xsct: gamma ramps for a color temperature, and setting them
+// through X11 RandR (x11 feature)
+
+use std::collections::HashMap;
+use std::fmt;
+use std::thread::sleep;
+use std::time::Duration;
+use log::debug;
+#[cfg(feature = "x11")]
+use x11rb::connection::{Connection, RequestConnection};
+#[cfg(feature = "x11")]
+use x11rb::protocol::randr::{self, Connection as OutputConnection, ConnectionExt as RandrExt};
+#[cfg(feature = "x11")]
+use x11rb::rust_connection::RustConnection;
+
+pub const TEMPERATURE_NORM: i32 = 6500;
+pub const TEMPERATURE_NIGHT: i32 = 4500;
+pub const TEMPERATURE_ZERO: i32 = 700;
+const GAMMA_MULT: f64 = 65535.0;
+const RAMP_EPSILON: f64 = 0.5 / GAMMA_MULT; // Tolerance of cached ramps: entries off by at most one LSB
+const GAMMA_K0GR: f64 = -1.47751309139817;
+const GAMMA_K1GR: f64 = 0.28590164772055;
+const GAMMA_K0BR: f64 = -4.38321650114872;
+const GAMMA_K1BR: f64 = 0.6212158769447;
+const GAMMA_K0RB: f64 = 1.75390204039018;
+const GAMMA_K1RB: f64 = -0.1150805671482;
+const GAMMA_K0GB: f64 = 1.49221604915144;
+const GAMMA_K1GB: f64 = -0.07513509588921;
+const FADE_STEP_MS: u64 = 40; // Minimum delay between two fade steps
+const FADE_MAX_STEPS: u64 = 50;
+#[cfg(feature = "x11")]
+const RANDR_MAJOR: u32 = 1; // Oldest RandR version with GetScreenResourcesCurrent
+#[cfg(feature = "x11")]
+const RANDR_MINOR: u32 = 3;
+const DISPLAY_GAMMA: f64 = 2.2; // sRGB-like displays
+const LIGHTNESS_EPSILON: f64 = 216.0 / 24389.0; // CIE L* linear segment below this luminance
+const LIGHTNESS_KAPPA: f64 = 24389.0 / 27.0;
+// Range of the Planckian locus approximation (Kim et al., 2002)
+const PLANCKIAN_MIN: f64 = 1667.0;
+const PLANCKIAN_MAX: f64 = 25000.0;
+const PLANCKIAN_SEARCH_STEPS: usize = 40; // Bisection steps reading a temperature back
+
+/// Display gamma exponents (red, green, blue) that leave the ramps linear
+pub const GAMMA_LINEAR: (f64, f64, f64) = (1.0, 1.0, 1.0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempStatus {
+    pub temp: i32,
+    pub brightness: f64,
+}
+
+/// Connect to the given X display (":1", ":0.1"), else the one named by
+/// $DISPLAY, with a clear error when there is no display at all (headless
+/// session, SSH without forwarding). Also returns the screen number of the
+/// display name, the connection covering all of its screens.
+#[cfg(feature = "x11")]
+pub fn open_display(name: Option<&str>) -> Result<(RustConnection, usize), Box<dyn std::error::Error>> {
+    let display = match (name, std::env::var("DISPLAY")) {
+        (Some(name), _) => name.to_string(),
+        (None, Ok(display)) if !display.is_empty() => display,
+        _ => return Err("no X11 display available (DISPLAY is not set)".into()),
+    };
+    let (conn, screen) = RustConnection::connect(Some(&display))
+        .map_err(|e| format!("cannot open X11 display '{}': {}", display, e))?;
+    check_randr(&conn).map_err(|e| format!("X11 display '{}': {}", display, e))?;
+    Ok((conn, screen))
+}
+
+// Gamma ramps need RandR 1.3 (GetScreenResourcesCurrent), missing from
+// some minimal servers and Xvfb builds
+#[cfg(feature = "x11")]
+fn check_randr(conn: &RustConnection) -> Result<(), Box<dyn std::error::Error>> {
+    if conn.extension_information(randr::X11_EXTENSION_NAME)?.is_none() {
+        return Err("RandR extension not available".into());
+    }
+    let version = conn.randr_query_version(RANDR_MAJOR, RANDR_MINOR)?.reply()?;
+    if (version.major_version, version.minor_version) < (RANDR_MAJOR, RANDR_MINOR) {
+        return Err(format!(
+            "RandR {}.{} or later needed, the server has {}.{}",
+            RANDR_MAJOR, RANDR_MINOR, version.major_version, version.minor_version
+        )
+        .into());
+    }
+    Ok(())
+}
+
+pub fn double_trim(x: f64, a: f64, b: f64) -> f64 {
+    if x < a {
+        a
+    } else if x > b {
+        b
+    } else {
+        x
+    }
+}
+
+#[cfg(feature = "x11")]
+pub fn get_sct_for_screen(
+    conn: &RustConnection,
+    screen: usize,
+    icrtc: i32,
+) -> Result<TempStatus, Box<dyn std::error::Error>> {
+    get_sct_for_screen_with(conn, screen, icrtc, WhitePoint::Empirical)
+}
+
+/// Like `get_sct_for_screen`, for ramps set with another white point
+#[cfg(feature = "x11")]
+pub fn get_sct_for_screen_with(
+    conn: &RustConnection,
+    screen: usize,
+    icrtc: i32,
+    white_point: WhitePoint,
+) -> Result<TempStatus, Box<dyn std::error::Error>> {
+    let root = conn.setup().roots[screen].root;
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+
+    let ncrtc = resources.crtcs.len();
+    let mut n = ncrtc;
+    let mut icrtc_start = icrtc;
+
+    if icrtc >= 0 && (icrtc as usize) < ncrtc {
+        n = 1;
+    } else {
+        icrtc_start = 0;
+    }
+
+    let mut tops = Vec::with_capacity(n);
+    for c in (icrtc_start as usize)..(icrtc_start as usize + n) {
+        let crtcxid = resources.crtcs[c];
+        let gamma = conn.randr_get_crtc_gamma(crtcxid)?.reply()?;
+
+        let size = gamma.red.len();
+        if size == 0 {
+            // Allocated but not driving a display
+            continue;
+        }
+        tops.push(RampTop {
+            values: (gamma.red[size - 1], gamma.green[size - 1], gamma.blue[size - 1]),
+            size,
+        });
+    }
+
+    Ok(estimate_temperature_with(&tops, white_point))
+}
+
+/// Last entry of the red, green and blue ramps of a CRTC, with the ramp size
+#[derive(Debug, Clone, Copy)]
+pub struct RampTop {
+    pub values: (u16, u16, u16),
+    pub size: usize,
+}
+
+/// Temperature and brightness that `sct_for_screen` would have set to get
+/// these ramp tops, averaged over the CRTCs (the inverse of
+/// `temperature_to_gamma`)
+pub fn estimate_temperature(tops: &[RampTop]) -> TempStatus {
+    estimate_temperature_with(tops, WhitePoint::Empirical)
+}
+
+/// Like `estimate_temperature`, for ramps set with another white point
+pub fn estimate_temperature_with(tops: &[RampTop], white_point: WhitePoint) -> TempStatus {
+    let mut gammar = 0.0;
+    let mut gammag = 0.0;
+    let mut gammab = 0.0;
+    // Sum of the top entries at full brightness: the ramps end at
+    // (size - 1) / size, so it depends on the ramp size
+    let mut full = 0.0;
+
+    // An empty ramp has no top entry to go by
+    for top in tops.iter().filter(|top| top.size > 0) {
+        gammar += f64::from(top.values.0);
+        gammag += f64::from(top.values.1);
+        gammab += f64::from(top.values.2);
+        full += GAMMA_MULT * (top.size - 1) as f64 / top.size as f64;
+    }
+
+    let mut brightness = if gammar > gammag { gammar } else { gammag };
+    brightness = if gammab > brightness {
+        gammab
+    } else {
+        brightness
+    };
+
+    let mut temp = 0.0;
+
+    if brightness > 0.0 && full > 0.0 {
+        gammar /= brightness;
+        gammag /= brightness;
+        gammab /= brightness;
+        brightness /= full;
+        brightness = double_trim(brightness, 0.0, 1.0);
+
+        debug!("Gamma: {}, {}, {}, brightness: {}", gammar, gammag, gammab, brightness);
+
+        temp = white_point.temperature(gammar, gammag, gammab);
+    } else {
+        brightness = double_trim(brightness, 0.0, 1.0);
+    }
+
+    TempStatus {
+        temp: (temp + 0.5) as i32,
+        brightness,
+    }
+}
+
+// Inverse of `temperature_to_gamma`, for multipliers whose largest is 1
+fn empirical_temperature(gammar: f64, gammag: f64, gammab: f64) -> f64 {
+    let gammad = gammab - gammar;
+    if gammad < 0.0 {
+        if gammab > 0.0 {
+            ((gammag + 1.0 + gammad - (GAMMA_K0GR + GAMMA_K0BR)) / (GAMMA_K1GR + GAMMA_K1BR)).exp()
+                + TEMPERATURE_ZERO as f64
+        } else if gammag > 0.0 {
+            ((gammag - GAMMA_K0GR) / GAMMA_K1GR).exp() + TEMPERATURE_ZERO as f64
+        } else {
+            TEMPERATURE_ZERO as f64
+        }
+    } else {
+        ((gammag + 1.0 - gammad - (GAMMA_K0GB + GAMMA_K0RB)) / (GAMMA_K1GB + GAMMA_K1RB)).exp()
+            + (TEMPERATURE_NORM - TEMPERATURE_ZERO) as f64
+    }
+}
+
+/// How a color temperature becomes channel multipliers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhitePoint {
+    /// xsct's fitted curves (`temperature_to_gamma`)
+    #[default]
+    Empirical,
+    /// Color of a blackbody on the Planckian locus in sRGB primaries,
+    /// neutral at 6500K like the fitted curves. Below 1667K, where the
+    /// locus approximation ends, the 1667K color is used.
+    Planckian,
+}
+
+impl WhitePoint {
+    /// Per-channel gamma multipliers (red, green, blue) for a color temperature
+    pub fn gamma(self, temp: i32) -> (f64, f64, f64) {
+        match self {
+            WhitePoint::Empirical => temperature_to_gamma(temp),
+            WhitePoint::Planckian => planckian_gamma(temp as f64),
+        }
+    }
+
+    // Temperature of multipliers whose largest is 1, the inverse of `gamma`
+    fn temperature(self, gammar: f64, gammag: f64, gammab: f64) -> f64 {
+        match self {
+            WhitePoint::Empirical => empirical_temperature(gammar, gammag, gammab),
+            WhitePoint::Planckian => planckian_temperature(gammar, gammag, gammab),
+        }
+    }
+}
+
+// Blackbody multipliers relative to the 6500K blackbody, the largest being
+// 1. The ramps scale the encoded signal, so the light ratios are taken to
+// the power 1 / DISPLAY_GAMMA.
+fn planckian_gamma(temp: f64) -> (f64, f64, f64) {
+    let (r, g, b) = blackbody_rgb(temp);
+    let (r0, g0, b0) = blackbody_rgb(TEMPERATURE_NORM as f64);
+    let (r, g, b) = ((r / r0).max(0.0), (g / g0).max(0.0), (b / b0).max(0.0));
+    let top = r.max(g).max(b);
+    let encode = |light: f64| (light / top).powf(1.0 / DISPLAY_GAMMA);
+    (encode(r), encode(g), encode(b))
+}
+
+// Linear sRGB (unit luminance) of the blackbody chromaticity, from the cubic
+// fits of the Planckian locus by Kim et al.
+fn blackbody_rgb(temp: f64) -> (f64, f64, f64) {
+    let t = temp.clamp(PLANCKIAN_MIN, PLANCKIAN_MAX);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t.powi(3) - 0.2343589e6 / t.powi(2) + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t.powi(3) + 2.1070379e6 / t.powi(2) + 0.2226347e3 / t + 0.240390
+    };
+    let y = if t <= 2222.0 {
+        -1.1063814 * x.powi(3) - 1.3481102 * x.powi(2) + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x.powi(3) - 1.37418593 * x.powi(2) + 2.09137015 * x - 0.16748867
+    } else {
+        3.081758 * x.powi(3) - 5.8733867 * x.powi(2) + 3.75112997 * x - 0.37001483
+    };
+    // XYZ with Y = 1, then the sRGB (D65) matrix
+    let (cx, cz) = (x / y, (1.0 - x - y) / y);
+    (
+        3.2404542 * cx - 1.5371385 - 0.4985314 * cz,
+        -0.969266 * cx + 1.8760108 + 0.041556 * cz,
+        0.0556434 * cx - 0.2040259 + 1.0572252 * cz,
+    )
+}
+
+// Inverse of `planckian_gamma` by bisection: green and blue relative to red
+// grow with the temperature
+fn planckian_temperature(gammar: f64, gammag: f64, gammab: f64) -> f64 {
+    if gammar <= 0.0 {
+        return PLANCKIAN_MAX;
+    }
+    let wanted = (gammag + gammab) / gammar;
+    let (mut low, mut high) = (TEMPERATURE_ZERO as f64, PLANCKIAN_MAX);
+    for _ in 0..PLANCKIAN_SEARCH_STEPS {
+        let middle = (low + high) / 2.0;
+        let (r, g, b) = planckian_gamma(middle);
+        if (g + b) / r < wanted {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Per-channel gamma multipliers (red, green, blue) for a color temperature
+pub fn temperature_to_gamma(temp: i32) -> (f64, f64, f64) {
+    let t = temp as f64;
+
+    if temp < TEMPERATURE_NORM {
+        let gammar = 1.0;
+        let (gammag, gammab) = if temp > TEMPERATURE_ZERO {
+            let g = (t - TEMPERATURE_ZERO as f64).ln();
+            (
+                double_trim(GAMMA_K0GR + GAMMA_K1GR * g, 0.0, 1.0),
+                double_trim(GAMMA_K0BR + GAMMA_K1BR * g, 0.0, 1.0),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        (gammar, gammag, gammab)
+    } else {
+        let g = (t - (TEMPERATURE_NORM - TEMPERATURE_ZERO) as f64).ln();
+        (
+            double_trim(GAMMA_K0RB + GAMMA_K1RB * g, 0.0, 1.0),
+            double_trim(GAMMA_K0GB + GAMMA_K1GB * g, 0.0, 1.0),
+            1.0,
+        )
+    }
+}
+
+/// Build red/green/blue gamma ramps of the given size. Each channel follows
+/// pow(i / size, 1 / exponent) before scaling, linear with `GAMMA_LINEAR`.
+pub fn build_gamma_ramps(
+    size: usize,
+    brightness: f64,
+    gamma: (f64, f64, f64),
+    exponents: (f64, f64, f64),
+) -> (Vec<u16>, Vec<u16>, Vec<u16>) {
+    let mut red = vec![0u16; size];
+    let mut green = vec![0u16; size];
+    let mut blue = vec![0u16; size];
+    fill_gamma_ramps((&mut red, &mut green, &mut blue), brightness, gamma, exponents);
+    (red, green, blue)
+}
+
+// Compute ramps of the same size in place
+fn fill_gamma_ramps(
+    (red, green, blue): (&mut [u16], &mut [u16], &mut [u16]),
+    brightness: f64,
+    (gammar, gammag, gammab): (f64, f64, f64),
+    (exponentr, exponentg, exponentb): (f64, f64, f64),
+) {
+    let size = red.len();
+    let g = GAMMA_MULT * brightness;
+    let (powr, powg, powb) = (1.0 / exponentr, 1.0 / exponentg, 1.0 / exponentb);
+    for i in 0..size {
+        let x = (i as f64) / (size as f64);
+        red[i] = (g * x.powf(powr) * gammar + 0.5) as u16;
+        green[i] = (g * x.powf(powg) * gammag + 0.5) as u16;
+        blue[i] = (g * x.powf(powb) * gammab + 0.5) as u16;
+    }
+}
+
+/// Gamma ramps kept from one call to the next, one set per ramp size, so
+/// that frequent updates (smooth transitions, fades) neither allocate nor
+/// recompute them for changes of at most one LSB per entry
+#[derive(Debug, Default)]
+pub struct RampCache {
+    ramps: HashMap<usize, CachedRamps>,
+}
+
+#[derive(Debug)]
+struct CachedRamps {
+    scale: (f64, f64, f64), // Brightness times the channel multipliers
+    exponents: (f64, f64, f64),
+    red: Vec<u16>,
+    green: Vec<u16>,
+    blue: Vec<u16>,
+}
+
+impl RampCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ramps of `size` entries, as `build_gamma_ramps` makes them. The cached
+    /// ones are returned while no channel moved by RAMP_EPSILON since they
+    /// were computed, so an entry may be one LSB from a fresh computation.
+    pub fn ramps(
+        &mut self,
+        size: usize,
+        brightness: f64,
+        gamma: (f64, f64, f64),
+        exponents: (f64, f64, f64),
+    ) -> (&[u16], &[u16], &[u16]) {
+        let scale = (brightness * gamma.0, brightness * gamma.1, brightness * gamma.2);
+        let cached = self.ramps.entry(size).or_insert_with(|| CachedRamps {
+            scale: (f64::NAN, f64::NAN, f64::NAN), // Never matches: computed below
+            exponents,
+            red: vec![0u16; size],
+            green: vec![0u16; size],
+            blue: vec![0u16; size],
+        });
+        let close = |a: f64, b: f64| (a - b).abs() < RAMP_EPSILON;
+        let unchanged = cached.exponents == exponents
+            && close(cached.scale.0, scale.0)
+            && close(cached.scale.1, scale.1)
+            && close(cached.scale.2, scale.2);
+        if !unchanged {
+            fill_gamma_ramps((&mut cached.red, &mut cached.green, &mut cached.blue), brightness, gamma, exponents);
+            cached.scale = scale;
+            cached.exponents = exponents;
+        }
+        (&cached.red, &cached.green, &cached.blue)
+    }
+}
+
+#[cfg(feature = "x11")]
+pub fn sct_for_screen(
+    conn: &RustConnection,
+    screen: usize,
+    icrtc: i32,
+    temp_status: TempStatus,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ramps = RampCache::new();
+    sct_for_screen_with_gamma(conn, screen, icrtc, temp_status, GAMMA_LINEAR, WhitePoint::Empirical, &mut ramps)
+}
+
+/// Like `sct_for_screen`, with display gamma exponents per channel, the
+/// white point computation and ramp buffers reused across calls
+#[cfg(feature = "x11")]
+pub fn sct_for_screen_with_gamma(
+    conn: &RustConnection,
+    screen: usize,
+    icrtc: i32,
+    temp_status: TempStatus,
+    exponents: (f64, f64, f64),
+    white_point: WhitePoint,
+    ramps: &mut RampCache,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let root = conn.setup().roots[screen].root;
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+
+    let b = double_trim(temp_status.brightness, 0.0, 1.0);
+    let (gammar, gammag, gammab) = white_point.gamma(temp_status.temp);
+
+    debug!("Gamma: {}, {}, {}, brightness: {}", gammar, gammag, gammab, b);
+
+    let ncrtc = resources.crtcs.len();
+    let mut n = ncrtc;
+    let mut icrtc_start = icrtc;
+
+    if icrtc >= 0 && (icrtc as usize) < ncrtc {
+        n = 1;
+    } else {
+        icrtc_start = 0;
+    }
+
+    for c in (icrtc_start as usize)..(icrtc_start as usize + n) {
+        let crtcxid = resources.crtcs[c];
+        let size_reply = conn.randr_get_crtc_gamma_size(crtcxid)?.reply()?;
+        let size = size_reply.size as usize;
+        if size == 0 {
+            continue;
+        }
+        let (red, green, blue) = ramps.ramps(size, b, (gammar, gammag, gammab), exponents);
+
+        conn.randr_set_crtc_gamma(crtcxid, red, green, blue)?;
+    }
+
+    Ok(())
+}
+
+/// Names of the outputs driven by each CRTC of a screen, indexed like the
+/// `icrtc` argument of `sct_for_screen` (disabled outputs are skipped)
+#[cfg(feature = "x11")]
+pub fn crtc_output_names(
+    conn: &RustConnection,
+    screen: usize,
+) -> Result<Vec<Vec<String>>, Box<dyn std::error::Error>> {
+    let root = conn.setup().roots[screen].root;
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+
+    let mut names = vec![Vec::new(); resources.crtcs.len()];
+    for &output in &resources.outputs {
+        let info = conn.randr_get_output_info(output, resources.config_timestamp)?.reply()?;
+        if let Some(c) = resources.crtcs.iter().position(|&crtc| crtc == info.crtc) {
+            names[c].push(String::from_utf8_lossy(&info.name).into_owned());
+        }
+    }
+
+    Ok(names)
+}
+
+/// A connected output of a screen, with the CRTC driving it (none when the
+/// output is turned off)
+#[derive(Debug, Clone)]
+pub struct OutputGamma {
+    pub name: String,
+    pub crtc: Option<CrtcGamma>,
+}
+
+/// CRTC index as in the `icrtc` argument of `sct_for_screen`, gamma ramp
+/// size and the temperature estimated from the ramp (0K for an empty one)
+#[derive(Debug, Clone, Copy)]
+pub struct CrtcGamma {
+    pub index: usize,
+    pub ramp_size: usize,
+    pub temp: TempStatus,
+}
+
+/// Connected outputs of a screen and the gamma of their CRTCs (read only)
+#[cfg(feature = "x11")]
+pub fn connected_outputs(conn: &RustConnection, screen: usize) -> Result<Vec<OutputGamma>, Box<dyn std::error::Error>> {
+    let root = conn.setup().roots[screen].root;
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+
+    let mut outputs = Vec::new();
+    for &output in &resources.outputs {
+        let info = conn.randr_get_output_info(output, resources.config_timestamp)?.reply()?;
+        if info.connection != OutputConnection::CONNECTED {
+            continue;
+        }
+        let crtc = match resources.crtcs.iter().position(|&crtc| crtc == info.crtc) {
+            Some(index) => {
+                let gamma = conn.randr_get_crtc_gamma(info.crtc)?.reply()?;
+                let size = gamma.red.len();
+                let tops: Vec<RampTop> = match size {
+                    0 => Vec::new(),
+                    _ => vec![RampTop {
+                        values: (gamma.red[size - 1], gamma.green[size - 1], gamma.blue[size - 1]),
+                        size,
+                    }],
+                };
+                Some(CrtcGamma { index, ramp_size: size, temp: estimate_temperature(&tops) })
+            }
+            None => None,
+        };
+        outputs.push(OutputGamma { name: String::from_utf8_lossy(&info.name).into_owned(), crtc });
+    }
+
+    Ok(outputs)
+}
+
+/// Adjustment made by `bound_temp` to get a displayable value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClampWarning {
+    TemperatureNotPositive, // Reset to TEMPERATURE_NORM
+    TemperatureTooLow,      // Raised to TEMPERATURE_ZERO
+    BrightnessTooLow,       // Raised to 0.0
+    BrightnessTooHigh,      // Lowered to 1.0
+}
+
+impl fmt::Display for ClampWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClampWarning::TemperatureNotPositive => write!(f, "Temperatures below 0 cannot be displayed."),
+            ClampWarning::TemperatureTooLow => {
+                write!(f, "Temperatures below {} cannot be displayed.", TEMPERATURE_ZERO)
+            }
+            ClampWarning::BrightnessTooLow => write!(f, "Brightness values below 0.0 cannot be displayed."),
+            ClampWarning::BrightnessTooHigh => write!(f, "Brightness values above 1.0 cannot be displayed."),
+        }
+    }
+}
+
+/// Clamp a temperature and brightness to displayable values. Returns the
+/// adjustments made, for the caller to report (or not).
+pub fn bound_temp(temp: &mut TempStatus) -> Vec<ClampWarning> {
+    let mut warnings = Vec::new();
+
+    if temp.temp <= 0 {
+        warnings.push(ClampWarning::TemperatureNotPositive);
+        temp.temp = TEMPERATURE_NORM;
+    } else if temp.temp < TEMPERATURE_ZERO {
+        warnings.push(ClampWarning::TemperatureTooLow);
+        temp.temp = TEMPERATURE_ZERO;
+    }
+
+    if temp.brightness < 0.0 {
+        warnings.push(ClampWarning::BrightnessTooLow);
+        temp.brightness = 0.0;
+    } else if temp.brightness > 1.0 {
+        warnings.push(ClampWarning::BrightnessTooHigh);
+        temp.brightness = 1.0;
+    }
+
+    warnings
+}
+
+/// Safety range of the temperature (--min-temp, --max-temp), the last clamp
+/// before the gamma whatever a profile, a delta or an override asks for.
+/// The default range is everything displayable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TempLimits {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl Default for TempLimits {
+    fn default() -> Self {
+        TempLimits { min: TEMPERATURE_ZERO, max: i32::MAX }
+    }
+}
+
+impl TempLimits {
+    /// Range from optional bounds, each defaulting to the displayable one
+    pub fn new(min: Option<i32>, max: Option<i32>) -> Result<Self, String> {
+        let limits = TempLimits {
+            min: min.unwrap_or(TEMPERATURE_ZERO),
+            max: max.unwrap_or(i32::MAX),
+        };
+        if limits.min > limits.max {
+            return Err(format!("--min-temp {}K is above --max-temp {}K", limits.min, limits.max));
+        }
+        Ok(limits)
+    }
+
+    /// `temp` with its temperature brought within the range
+    pub fn clamp(self, temp: TempStatus) -> TempStatus {
+        TempStatus { temp: temp.temp.clamp(self.min, self.max), ..temp }
+    }
+}
+
+/// Parse a --min-temp or --max-temp value, in displayable Kelvin
+pub fn parse_temp_limit(value: &str) -> Result<i32, String> {
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|&kelvin| kelvin >= TEMPERATURE_ZERO)
+        .ok_or_else(|| format!("invalid temperature limit '{}' (expected {}K or more)", value, TEMPERATURE_ZERO))
+}
+
+/// Target of xsct -t: night temperature when the screen is at (about) the
+/// day temperature, day temperature otherwise. The brightness is kept.
+pub fn toggle_temperature(current: TempStatus) -> TempStatus {
+    let temp = if current.temp > TEMPERATURE_NORM - 100 {
+        TEMPERATURE_NIGHT
+    } else {
+        TEMPERATURE_NORM
+    };
+    TempStatus { temp, brightness: current.brightness }
+}
+
+/// Target of xsct -d: the current setting shifted by the deltas, a missing
+/// delta counting as no change. An unchanged temperature is kept as read, so
+/// dimming alone does not move it (on a black screen, where it cannot be
+/// read back, it becomes neutral).
+pub fn apply_delta(
+    current: TempStatus,
+    temp_delta: Option<i32>,
+    brightness_delta: Option<f64>,
+) -> (TempStatus, Vec<ClampWarning>) {
+    let temp = match temp_delta {
+        Some(delta) if delta != 0 => current.temp + delta,
+        _ if current.temp <= 0 => TEMPERATURE_NORM,
+        _ => current.temp,
+    };
+    let mut target = TempStatus { temp, brightness: current.brightness + brightness_delta.unwrap_or(0.0) };
+    let warnings = bound_temp(&mut target);
+    (target, warnings)
+}
+
+/// How the brightness moves between two values during a fade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BrightnessScale {
+    /// Straight line between the two multipliers
+    #[default]
+    Linear,
+    /// Even steps of perceived lightness: the multiplier scales the encoded
+    /// signal, so the light follows it to the power of the display gamma and
+    /// the eye sees the CIE lightness (L*) of that light
+    Perceptual,
+}
+
+impl BrightnessScale {
+    /// Brightness `ratio` (0 to 1) of the way from `from` to `to`
+    pub fn interpolate(self, from: f64, to: f64, ratio: f64) -> f64 {
+        match self {
+            BrightnessScale::Linear => from + (to - from) * ratio,
+            BrightnessScale::Perceptual => {
+                let (from, to) = (lightness(from), lightness(to));
+                brightness_of_lightness(from + (to - from) * ratio)
+            }
+        }
+    }
+}
+
+// CIE L* (0 to 100) of the light a brightness multiplier gives
+fn lightness(brightness: f64) -> f64 {
+    let luminance = brightness.clamp(0.0, 1.0).powf(DISPLAY_GAMMA);
+    if luminance > LIGHTNESS_EPSILON {
+        116.0 * luminance.cbrt() - 16.0
+    } else {
+        luminance * LIGHTNESS_KAPPA
+    }
+}
+
+// Inverse of `lightness`
+fn brightness_of_lightness(lightness: f64) -> f64 {
+    let luminance = if lightness > LIGHTNESS_EPSILON * LIGHTNESS_KAPPA {
+        ((lightness + 16.0) / 116.0).powi(3)
+    } else {
+        lightness / LIGHTNESS_KAPPA
+    };
+    luminance.clamp(0.0, 1.0).powf(1.0 / DISPLAY_GAMMA)
+}
+
+/// Step from one temperature to another over `fade_ms` milliseconds, the
+/// brightness following `scale`. The number of steps is bounded so RandR is
+/// not flooded with gamma updates.
+pub fn fade(
+    from: TempStatus,
+    to: TempStatus,
+    fade_ms: u64,
+    scale: BrightnessScale,
+    mut apply: impl FnMut(TempStatus),
+) {
+    let steps = (fade_ms / FADE_STEP_MS).clamp(1, FADE_MAX_STEPS);
+    let delay = Duration::from_millis(fade_ms / steps);
+
+    for i in 1..=steps {
+        let ratio = i as f64 / steps as f64;
+        apply(TempStatus {
+            temp: from.temp + ((to.temp - from.temp) as f64 * ratio).round() as i32,
+            brightness: scale.interpolate(from.brightness, to.brightness, ratio),
+        });
+        if i < steps {
+            sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_switches_day_and_night() {
+        let day = TempStatus { temp: 6480, brightness: 0.7 };
+        let night = toggle_temperature(day);
+        assert_eq!(night.temp, TEMPERATURE_NIGHT);
+        assert_eq!(night.brightness, 0.7);
+
+        let back = toggle_temperature(night);
+        assert_eq!(back.temp, TEMPERATURE_NORM);
+        assert_eq!(back.brightness, 0.7);
+    }
+
+    #[test]
+    fn brightness_only_delta_keeps_the_temperature() {
+        let current = TempStatus { temp: 4321, brightness: 0.8 };
+        let (target, warnings) = apply_delta(current, None, Some(-0.1));
+        assert_eq!(target.temp, 4321);
+        assert!((target.brightness - 0.7).abs() < 1e-9);
+        assert!(warnings.is_empty());
+
+        let (target, _) = apply_delta(current, Some(0), Some(-0.1));
+        assert_eq!(target.temp, 4321);
+    }
+
+    #[test]
+    fn temperature_only_delta_keeps_the_brightness() {
+        let current = TempStatus { temp: 6500, brightness: 0.6 };
+        let (target, warnings) = apply_delta(current, Some(-500), None);
+        assert_eq!(target, TempStatus { temp: 6000, brightness: 0.6 });
+        assert!(warnings.is_empty());
+    }
+
+    // A black screen reads back as 0K, which is not a temperature to warn about
+    #[test]
+    fn brightening_a_black_screen() {
+        let black = TempStatus { temp: 0, brightness: 0.0 };
+        let (target, warnings) = apply_delta(black, None, Some(0.5));
+        assert_eq!(target, TempStatus { temp: TEMPERATURE_NORM, brightness: 0.5 });
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn delta_is_clamped() {
+        let current = TempStatus { temp: 1000, brightness: 0.95 };
+        let (target, warnings) = apply_delta(current, Some(-500), Some(0.1));
+        assert_eq!(target, TempStatus { temp: TEMPERATURE_ZERO, brightness: 1.0 });
+        assert_eq!(warnings, vec![ClampWarning::TemperatureTooLow, ClampWarning::BrightnessTooHigh]);
+    }
+
+    #[test]
+    fn temperature_limits() {
+        let limits = TempLimits::new(Some(3000), Some(6500)).unwrap();
+        let (target, _) = apply_delta(TempStatus { temp: 3200, brightness: 0.8 }, Some(-1000), None);
+        assert_eq!(limits.clamp(target), TempStatus { temp: 3000, brightness: 0.8 });
+        assert_eq!(limits.clamp(TempStatus { temp: 9000, brightness: 1.0 }).temp, 6500);
+        assert_eq!(limits.clamp(TempStatus { temp: 4500, brightness: 1.0 }).temp, 4500);
+        assert_eq!(TempLimits::new(None, None), Ok(TempLimits::default()));
+        assert!(TempLimits::new(Some(5000), Some(4000)).is_err());
+        assert_eq!(parse_temp_limit("3000"), Ok(3000));
+        assert!(parse_temp_limit("500").is_err());
+        assert!(parse_temp_limit("warm").is_err());
+    }
+
+    // What sct_for_screen sets, get_sct_for_screen reads back
+    #[test]
+    fn estimate_round_trip() {
+        for size in [256, 1024, 2048, 4096] {
+            for brightness in [1.0, 0.7, 0.3] {
+                for temp in (1000..=10000).step_by(50) {
+                    let (red, green, blue) = build_gamma_ramps(size, brightness, temperature_to_gamma(temp), GAMMA_LINEAR);
+                    let top = RampTop { values: (red[size - 1], green[size - 1], blue[size - 1]), size };
+                    let estimate = estimate_temperature(&[top, top]);
+                    assert!((estimate.temp - temp).abs() <= 1, "{}K read as {}K (size {})", temp, estimate.temp, size);
+                    assert!(
+                        (estimate.brightness - brightness).abs() < 0.001,
+                        "brightness {} read as {} (size {})",
+                        brightness,
+                        estimate.brightness,
+                        size
+                    );
+                }
+            }
+        }
+    }
+
+    // Reading and setting again, as xsct -d 0 does, must not walk away
+    #[test]
+    fn repeated_round_trips_do_not_drift() {
+        for temp in (1000..=10000).step_by(250) {
+            let mut current = TempStatus { temp, brightness: 0.8 };
+            for _ in 0..20 {
+                let (red, green, blue) = build_gamma_ramps(1024, current.brightness, temperature_to_gamma(current.temp), GAMMA_LINEAR);
+                current = estimate_temperature(&[RampTop { values: (red[1023], green[1023], blue[1023]), size: 1024 }]);
+            }
+            assert!((current.temp - temp).abs() <= 5, "{}K drifted to {}K", temp, current.temp);
+            assert!((current.brightness - 0.8).abs() < 0.001);
+        }
+    }
+
+    // A CRTC allocated without a display reports an empty ramp
+    #[test]
+    fn empty_ramps_are_ignored() {
+        let (red, green, blue) = build_gamma_ramps(1024, 1.0, temperature_to_gamma(4500), GAMMA_LINEAR);
+        let top = RampTop { values: (red[1023], green[1023], blue[1023]), size: 1024 };
+        let empty = RampTop { values: (0, 0, 0), size: 0 };
+        assert_eq!(estimate_temperature(&[top, empty]), estimate_temperature(&[top]));
+        assert_eq!(estimate_temperature(&[empty]), TempStatus { temp: 0, brightness: 0.0 });
+        assert!(build_gamma_ramps(0, 1.0, temperature_to_gamma(4500), GAMMA_LINEAR).0.is_empty());
+    }
+
+    // Same ramps as built from scratch, recomputed once they would differ
+    #[test]
+    fn ramp_cache() {
+        let mut cache = RampCache::new();
+        let gamma = temperature_to_gamma(4500);
+        let built = build_gamma_ramps(1024, 0.8, gamma, GAMMA_LINEAR);
+        let (red, green, blue) = cache.ramps(1024, 0.8, gamma, GAMMA_LINEAR);
+        assert_eq!((red.to_vec(), green.to_vec(), blue.to_vec()), built);
+
+        // Below half a step of the top entry: the cached ramps
+        let (_, _, blue) = cache.ramps(1024, 0.8 + 1e-7, gamma, GAMMA_LINEAR);
+        assert_eq!(blue, &built.2[..]);
+
+        let dimmer = build_gamma_ramps(1024, 0.7, gamma, GAMMA_LINEAR);
+        let (red, _, blue) = cache.ramps(1024, 0.7, gamma, GAMMA_LINEAR);
+        assert_eq!((red, blue), (&dimmer.0[..], &dimmer.2[..]));
+        let (red, _, _) = cache.ramps(256, 0.7, gamma, GAMMA_LINEAR);
+        assert_eq!(red, &build_gamma_ramps(256, 0.7, gamma, GAMMA_LINEAR).0[..]);
+        let (_, green, _) = cache.ramps(1024, 0.7, gamma, (1.0, 2.2, 1.0));
+        assert_eq!(green, &build_gamma_ramps(1024, 0.7, gamma, (1.0, 2.2, 1.0)).1[..]);
+    }
+
+    // Neutral at 6500K like the fitted curves, redshift's blackbody color at
+    // 3000K (1.0, 0.72, 0.43), and apart from the fitted curves in the warm end
+    #[test]
+    fn planckian_white_point() {
+        let close = |a: (f64, f64, f64), b: (f64, f64, f64), limit: f64| {
+            (a.0 - b.0).abs() < limit && (a.1 - b.1).abs() < limit && (a.2 - b.2).abs() < limit
+        };
+        let planckian = |temp| WhitePoint::Planckian.gamma(temp);
+        assert!(close(planckian(TEMPERATURE_NORM), (1.0, 1.0, 1.0), 1e-9));
+        assert!(close(planckian(3000), (1.0, 0.72, 0.43), 0.02), "{:?}", planckian(3000));
+        assert!((planckian(2000).2 - temperature_to_gamma(2000).2).abs() > 0.03);
+        assert_eq!(planckian(1000), planckian(1667));
+
+        for temp in (1700..=20000).step_by(100) {
+            let (red, green, blue) = build_gamma_ramps(1024, 0.8, planckian(temp), GAMMA_LINEAR);
+            let top = RampTop { values: (red[1023], green[1023], blue[1023]), size: 1024 };
+            let estimate = estimate_temperature_with(&[top], WhitePoint::Planckian);
+            let limit = temp / 200;
+            assert!((estimate.temp - temp).abs() <= limit, "{}K read as {}K", temp, estimate.temp);
+        }
+    }
+
+    #[test]
+    fn perceptual_brightness_fade() {
+        let scale = BrightnessScale::Perceptual;
+        for brightness in [0.0, 0.05, 0.3, 0.7, 1.0] {
+            assert!((scale.interpolate(brightness, 0.5, 0.0) - brightness).abs() < 1e-9);
+            assert!((scale.interpolate(0.5, brightness, 1.0) - brightness).abs() < 1e-9);
+        }
+        // Lightness grows fastest at low brightness, so its midpoint lies
+        // below the linear one
+        let halfway = scale.interpolate(1.0, 0.4, 0.5);
+        assert!(halfway < BrightnessScale::Linear.interpolate(1.0, 0.4, 0.5));
+        assert!((lightness(halfway) - (lightness(1.0) + lightness(0.4)) / 2.0).abs() < 1e-9);
+        let steps: Vec<f64> = (0..=10).map(|i| scale.interpolate(1.0, 0.4, i as f64 / 10.0)).collect();
+        assert!(steps.windows(2).all(|w| w[0] > w[1]), "{:?}", steps);
+    }
+}